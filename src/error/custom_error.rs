@@ -12,15 +12,151 @@ use polars::error::PolarsError;
 pub type SsqlResult<T> = Result<T, SsqlError>;
 
 /// Error enum representing different errors during execution.
+/// Errors returned by SQL Server are classified by their error number,
+/// the statement that failed being attached for debugging, see [`ServerError`].
+///
+/// [`ServerError`]: struct.ServerError.html
 #[derive(Debug)]
 pub enum SsqlError {
-    /// An Error occurs when executing sql.
-    SqlServerError(tiberius::error::Error),
+    /// An Error occurs when executing sql, not classified in other variants.
+    SqlServerError(ServerError),
     /// An Error occurs when transforming result to polars dataframe.
     #[cfg(feature = "polars")]
     PolarsError(PolarsError),
     /// An Error occurs when constructing query.
     RsRunningError(String),
+    /// Row to update or delete was modified or deleted since it was loaded,
+    /// detected by the `#[ssql(version)]` column. Contains the table name.
+    ConcurrencyConflict(String),
+    /// Column is not a field of the struct, or is missing in the result set.
+    ColumnNotFound {
+        /// Table of the struct, empty for raw structs.
+        table: String,
+        /// Name of the column.
+        column: String,
+    },
+    /// Filter or ordering refers to a table not joined in the query builder. Contains the table name.
+    TableNotInBuilder(String),
+    /// Tables are joined without relationship set by `#[ssql(foreign_key = ...)]`.
+    RelationshipNotFound {
+        /// Main table of the query builder.
+        from: String,
+        /// Joined table.
+        to: String,
+    },
+    /// Value of a column can not be decoded into the field of the struct.
+    Decode {
+        /// Table of the struct, empty for raw structs.
+        table: String,
+        /// Name of the column.
+        column: String,
+        /// Rust type of the field.
+        expected: String,
+        /// SQL type of the value, `NULL`, or the error of a custom conversion.
+        found: String,
+    },
+    /// Violation of a primary key or unique constraint or index, error 2627 or 2601.
+    UniqueViolation(ServerError),
+    /// Violation of a foreign key or check constraint, error 547.
+    ForeignKeyViolation(ServerError),
+    /// Transaction chosen as deadlock victim, error 1205.
+    Deadlock(ServerError),
+    /// Lock request timed out (error 1222) or the connection timed out.
+    Timeout(ServerError),
+    /// Query expected to return a row returned none. Contains the table name.
+    NoRows(String),
+}
+
+/// Error returned by SQL Server or the connection, with the statement being executed if known.
+#[derive(Debug, Clone)]
+pub struct ServerError {
+    error: tiberius::error::Error,
+    sql: Option<String>,
+}
+
+impl ServerError {
+    /// Error number of SQL Server, `None` for errors of the connection.
+    pub fn code(&self) -> Option<u32> {
+        self.error.code()
+    }
+
+    /// Statement that failed.
+    pub fn sql(&self) -> Option<&str> {
+        self.sql.as_deref()
+    }
+
+    /// Underlying error of tiberius.
+    pub fn inner(&self) -> &tiberius::error::Error {
+        &self.error
+    }
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.sql {
+            Some(sql) => write!(f, "{}, sql: {}", self.error, sql),
+            None => write!(f, "{}", self.error),
+        }
+    }
+}
+
+// errors of Azure SQL Database that go away when retried, such as a database being moved or throttled
+const TRANSIENT_CODES: [u32; 14] = [
+    233, 4060, 4221, 10053, 10054, 10060, 10928, 10929, 40197, 40501, 40613, 49918, 49919, 49920,
+];
+
+impl SsqlError {
+    /// Classify an error of tiberius by its number, attaching the statement being executed.
+    #[doc(hidden)]
+    pub fn with_sql(error: tiberius::error::Error, sql: &str) -> Self {
+        Self::classify(error, Some(sql.to_string()))
+    }
+
+    fn classify(error: tiberius::error::Error, sql: Option<String>) -> Self {
+        let timed_out = matches!(
+            &error,
+            tiberius::error::Error::Io { kind, .. } if *kind == tiberius::error::IoErrorKind::TimedOut
+        );
+        let code = error.code();
+        let error = ServerError { error, sql };
+        match code {
+            Some(2627 | 2601) => SsqlError::UniqueViolation(error),
+            Some(547) => SsqlError::ForeignKeyViolation(error),
+            Some(1205) => SsqlError::Deadlock(error),
+            Some(1222) => SsqlError::Timeout(error),
+            _ if timed_out => SsqlError::Timeout(error),
+            _ => SsqlError::SqlServerError(error),
+        }
+    }
+
+    /// Error returned by SQL Server or the connection, if any.
+    pub fn server_error(&self) -> Option<&ServerError> {
+        match self {
+            SsqlError::SqlServerError(e)
+            | SsqlError::UniqueViolation(e)
+            | SsqlError::ForeignKeyViolation(e)
+            | SsqlError::Deadlock(e)
+            | SsqlError::Timeout(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// Statement that failed, if the error was returned by SQL Server.
+    pub fn sql(&self) -> Option<&str> {
+        self.server_error().and_then(|e| e.sql())
+    }
+
+    /// Whether executing the same statement again may succeed:
+    /// deadlocks, timeouts and transient errors of Azure SQL Database.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            SsqlError::Deadlock(_) | SsqlError::Timeout(_) => true,
+            SsqlError::SqlServerError(e) => e
+                .code()
+                .is_some_and(|code| TRANSIENT_CODES.contains(&code)),
+            _ => false,
+        }
+    }
 }
 
 impl std::error::Error for SsqlError {}
@@ -46,6 +182,39 @@ impl fmt::Display for SsqlError {
                 format!("Error occur when transforming to polars: {}", inner)
             }
             SsqlError::RsRunningError(inner) => inner.to_string(),
+            SsqlError::ConcurrencyConflict(table) => {
+                format!("Row in {} was modified or deleted by another transaction", table)
+            }
+            SsqlError::ColumnNotFound { table, column } => match table.is_empty() {
+                true => format!("column {} not found", column),
+                false => format!("column {} not found in {}", column, table),
+            },
+            SsqlError::TableNotInBuilder(table) => {
+                format!("table {} is not in this builder", table)
+            }
+            SsqlError::RelationshipNotFound { from, to } => format!(
+                "no relationship between {} and {}, set one with #[ssql(foreign_key = ...)]",
+                from, to
+            ),
+            SsqlError::Decode {
+                table,
+                column,
+                expected,
+                found,
+            } => match table.is_empty() {
+                true => format!("failed to decode column {} into {}, found {}", column, expected, found),
+                false => format!(
+                    "failed to decode column {} of table {} into {}, found {}",
+                    column, table, expected, found
+                ),
+            },
+            SsqlError::UniqueViolation(inner) => format!("Unique constraint violated: {}", inner),
+            SsqlError::ForeignKeyViolation(inner) => {
+                format!("Foreign key or check constraint violated: {}", inner)
+            }
+            SsqlError::Deadlock(inner) => format!("Chosen as deadlock victim: {}", inner),
+            SsqlError::Timeout(inner) => format!("Timed out: {}", inner),
+            SsqlError::NoRows(table) => format!("no row returned from {}", table),
         };
         write!(f, "{}", a)
     }
@@ -58,9 +227,16 @@ impl From<PolarsError> for SsqlError {
     }
 }
 
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for SsqlError {
+    fn from(value: serde_json::Error) -> Self {
+        SsqlError::RsRunningError(format!("failed to serialize to json: {}", value))
+    }
+}
+
 impl From<tiberius::error::Error> for SsqlError {
     fn from(value: tiberius::error::Error) -> Self {
-        SsqlError::SqlServerError(value)
+        SsqlError::classify(value, None)
     }
 }
 
@@ -50,6 +50,8 @@
 //! Filters can be applied to query builder via provided [`filter`] method.
 //! Filters can be chained.
 //! For all filter expression please refer to [`ColExpr`].
+//! Each field also gets a typed [`Column`] constant named in upper case, such as `Person::EMAIL`,
+//! checked at compile time against the field name and type.
 //! ```no_run
 //! # use ssql::prelude::*;
 //! # use serde::{Deserialize, Serialize};
@@ -66,16 +68,19 @@
 //! ).filter(
 //!     Person::col("id")?.gt(&3)
 //! );
+//! let query = query.filter(Person::ID.gt(&3));
 //! ```
 //!
 //! # Manipulating Data
-//! Data can be [`insert`],[`delete`],[`update`],[`insert_ignore_pk`] for any instance that `#[derive(ORM)]` and set `#[ssql(primary_key)]`.
+//! Data can be [`insert`],[`insert_ignore_pk`] for any instance that `#[derive(ORM)]`,
+//! and [`delete`],[`update`] if it sets `#[ssql(primary_key)]`, implementing [`SsqlKeyed`].
 //! Or calling `bulk insert` with [`Struct::insert_many(&mut conn)`] method.
 //!
-//! [`insert`]: trait.SsqlMarker.html#tymethod.insert
-//! [`delete`]: trait.SsqlMarker.html#tymethod.delete
-//! [`update`]: trait.SsqlMarker.html#tymethod.update
-//! [`insert_ignore_pk`]: trait.SsqlMarker.html#tymethod.insert_ignore_pk
+//! [`insert`]: trait.SsqlMarker.html#method.insert
+//! [`delete`]: trait.SsqlKeyed.html#method.delete
+//! [`update`]: trait.SsqlKeyed.html#method.update
+//! [`insert_ignore_pk`]: trait.SsqlMarker.html#method.insert_ignore_pk
+//! [`SsqlKeyed`]: trait.SsqlKeyed.html
 //! [`Struct::insert_many(&mut conn)`]: trait.SsqlMarker.html#tymethod.insert_many
 //! ```
 //! # use ssql::prelude::*;
@@ -123,6 +128,212 @@
 //! }
 //! ```
 //!
+//! # Field Attributes
+//! Fields map to columns of the same name, this can be changed per field.
+//! Identifiers are always quoted with brackets, so reserved words and names with spaces are fine.
+//! ```
+//! use ssql::prelude::*;
+//! # use chrono::NaiveDateTime;
+//!  #[derive(ORM)]
+//!  #[ssql(table = "Order", schema = sales)] // string literal for names that are not identifiers
+//!  pub struct Order {
+//!     #[ssql(primary_key)]
+//!     id: i32,
+//!     #[ssql(rename = "Order Date")] // column name differs from field name
+//!     date: NaiveDateTime,
+//!     #[ssql(readonly)] // or computed, selected but never inserted or updated
+//!     total: f64,
+//!     #[ssql(skip)] // not persisted, filled with Default when loading
+//!     cache: Vec<String>,
+//!  }
+//! ```
+//!
+//! Fields with a `foreign_key` to the same table form a composite foreign key, joins matching all of them.
+//! Fields referring to the same column are independent relationships, joins use the first one declared.
+//! ```
+//! use ssql::prelude::*;
+//!  #[derive(ORM)]
+//!  #[ssql(table = posts)]
+//!  pub struct Post {
+//!     #[ssql(primary_key)]
+//!     id: i32,
+//!     #[ssql(foreign_key = "users.id")] // joined on posts.created_by = users.id
+//!     created_by: i32,
+//!     #[ssql(foreign_key = "users.id")]
+//!     updated_by: i32,
+//!  }
+//! ```
+//!
+//! Types not supported by tiberius can be stored with `#[ssql(with = "module")]`,
+//! the module converts the field from and to a sql value.
+//! In [`json`] and [`df`] results such fields hold the text of the stored value.
+//! ```
+//! use ssql::prelude::*;
+//!  mod comma_list {
+//!     use ssql::prelude::*;
+//!     pub fn to_sql(value: &[String]) -> ColumnData<'static> {
+//!         ColumnData::String(Some(value.join(",").into()))
+//!     }
+//!     pub fn from_sql(data: &ColumnData<'static>) -> SsqlResult<Vec<String>> {
+//!         let text: Option<&str> = tiberius::FromSql::from_sql(data)?;
+//!         Ok(text.map(|t| t.split(',').map(String::from).collect()).unwrap_or_default())
+//!     }
+//!  }
+//!
+//!  #[derive(ORM)]
+//!  #[ssql(table = person)]
+//!  pub struct Person {
+//!     #[ssql(primary_key)]
+//!     id: i32,
+//!     #[ssql(with = "comma_list")]
+//!     tags: Vec<String>,
+//!  }
+//! ```
+//!
+//! With feature `serde`, fields of any `Serialize + DeserializeOwned` type marked `#[ssql(json)]`
+//! are stored as JSON documents in text columns and returned as parsed documents in [`json`] results.
+//! Values inside them are filtered with paths following the column name, see [`ColExpr::json`].
+//! ```
+//! use ssql::prelude::*;
+//! # use serde::{Deserialize, Serialize};
+//!  #[derive(Serialize, Deserialize)]
+//!  pub struct Payload {
+//!     customer_id: i32,
+//!     tags: Vec<String>,
+//!  }
+//!
+//!  #[derive(ORM)]
+//!  #[ssql(table = events)]
+//!  pub struct Event {
+//!     #[ssql(primary_key)]
+//!     id: i32,
+//!     #[ssql(json)]
+//!     payload: Payload,
+//!  }
+//! # fn _test() -> SsqlResult<()> {
+//! let query = Event::query()
+//!     .filter(Event::col("payload.$.customer_id")?.eq(&42))?
+//!     .filter(Event::col("payload.$.tags")?.json_contains(&"vip"))?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Columns repeated across tables, such as the parts of an address, are grouped into a struct
+//! deriving [`ColumnGroup`] and stored in place with `#[ssql(flatten)]`.
+//!
+//! # Attribute Errors
+//! Attributes are checked when deriving, mistakes are reported at the attribute or field at fault.
+//! Unknown attributes are rejected, on the struct only `table` and `schema` are expected.
+//! ```compile_fail
+//! use ssql::prelude::*;
+//!  #[derive(ORM)]
+//!  #[ssql(table = person, shema = dbo)]
+//!  pub struct Person {
+//!     #[ssql(primary_key)]
+//!     id: i32,
+//!  }
+//! ```
+//! ```compile_fail
+//! use ssql::prelude::*;
+//!  #[derive(ORM)]
+//!  #[ssql(table = person)]
+//!  pub struct Person {
+//!     #[ssql(primary)]
+//!     id: i32,
+//!  }
+//! ```
+//!
+//! Foreign keys are given as `"table.column"` or `"schema.table.column"`.
+//! ```compile_fail
+//! use ssql::prelude::*;
+//!  #[derive(ORM)]
+//!  #[ssql(table = posts)]
+//!  pub struct Post {
+//!     #[ssql(primary_key)]
+//!     id: i32,
+//!     #[ssql(foreign_key = "users")]
+//!     user_id: i32,
+//!  }
+//! ```
+//!
+//! Fields map to columns by name, so tuple structs can not be derived.
+//! ```compile_fail
+//! use ssql::prelude::*;
+//!  #[derive(ORM)]
+//!  #[ssql(table = person)]
+//!  pub struct Person(i32, String);
+//! ```
+//!
+//! A table has one row version at most.
+//! ```compile_fail
+//! use ssql::prelude::*;
+//!  #[derive(ORM)]
+//!  #[ssql(table = person)]
+//!  pub struct Person {
+//!     #[ssql(primary_key)]
+//!     id: i32,
+//!     #[ssql(version)]
+//!     row_ver: Vec<u8>,
+//!     #[ssql(version)]
+//!     other_ver: Vec<u8>,
+//!  }
+//! ```
+//!
+//! `json` fields are converted by [`json_column`], they can not name another module with `with`.
+//! ```compile_fail
+//! use ssql::prelude::*;
+//! # mod comma_list {}
+//!  #[derive(ORM)]
+//!  #[ssql(table = person)]
+//!  pub struct Person {
+//!     #[ssql(primary_key)]
+//!     id: i32,
+//!     #[ssql(json, with = "comma_list")]
+//!     tags: Vec<String>,
+//!  }
+//! ```
+//!
+//! Flattened fields take no other attribute than `prefix`, which is only valid on them.
+//! ```compile_fail
+//! use ssql::prelude::*;
+//!  #[derive(ColumnGroup)]
+//!  pub struct Address {
+//!     street: String,
+//!  }
+//!
+//!  #[derive(ORM)]
+//!  #[ssql(table = person)]
+//!  pub struct Person {
+//!     #[ssql(primary_key)]
+//!     id: i32,
+//!     #[ssql(flatten, rename = "home")]
+//!     address: Address,
+//!  }
+//! ```
+//! ```compile_fail
+//! use ssql::prelude::*;
+//!  #[derive(ORM)]
+//!  #[ssql(table = person)]
+//!  pub struct Person {
+//!     #[ssql(primary_key)]
+//!     id: i32,
+//!     #[ssql(prefix = "home_")]
+//!     street: String,
+//!  }
+//! ```
+//!
+//! Skipped fields are not stored, so they can not identify or refer to rows.
+//! ```compile_fail
+//! use ssql::prelude::*;
+//!  #[derive(ORM)]
+//!  #[ssql(table = person)]
+//!  pub struct Person {
+//!     #[ssql(primary_key, skip)]
+//!     id: i32,
+//!     email: String,
+//!  }
+//! ```
+//!
 //! # Raw Sql Query
 //! Using [`raw_query`] method to construct a raw sql query.
 //! Field name are reflecting as column name in sql query result.
@@ -151,6 +362,12 @@
 //! [`ColExpr`]: structs.filter.ColExpr.html
 //! [`QueryBuilder`]: struct.QueryBuilder.html
 //! [`raw_query`]: trait.SsqlMarker.html#method.raw_query
+//! [`json`]: trait.QueryAble.html#method.json
+//! [`ColExpr::json`]: struct.ColExpr.html#method.json
+//! [`Column`]: struct.Column.html
+//! [`ColumnGroup`]: trait.ColumnGroup.html
+//! [`df`]: trait.QueryAble.html#method.df
+//! [`json_column`]: json_column/index.html
 #![warn(missing_docs)]
 #[macro_use]
 pub(crate) mod macros;
@@ -163,14 +380,33 @@ pub mod prelude;
 /// Utility functions.
 pub mod utils;
 
+pub use error::custom_error::ServerError;
 pub use error::custom_error::SsqlError;
 pub use error::custom_error::SsqlResult;
 
 pub use structs::JoinArg;
+pub use structs::bulk_insert::{BulkOptions, BulkProgress};
+pub use structs::column::{Column, ColumnValue};
+pub use structs::column_group::ColumnGroup;
+#[cfg(feature = "polars")]
+#[doc(hidden)]
+pub use structs::dataframe::ToSeries;
 pub use structs::filter::ColExpr;
 pub use structs::filter::FilterExpr;
+pub use structs::filter::InListStrategy;
+#[cfg(feature = "serde")]
+pub use structs::json_column;
+pub use structs::ssql_enum::{enum_column, SsqlEnum};
+#[doc(hidden)]
+pub use structs::ssql_enum::FieldValue;
+pub use structs::ssql_keyed::SsqlKeyed;
 pub use structs::ssql_marker::SsqlMarker;
 pub use structs::query_builder::QueryAble;
+#[cfg(feature = "polars")]
+pub use structs::raw_query_builder::UntypedRawQuery;
+pub use structs::retry::{Attempt, Retry, RetryFuture, RetryPolicy};
 pub use structs::stream::RowStream;
+pub use structs::table_scope::TableScope;
+pub use structs::tracked::Tracked;
 
 pub use structs::query_builder::QueryBuilderI;
\ No newline at end of file
@@ -8,12 +8,22 @@ pub use serde::Serialize;
 pub use serde_json::value::Serializer;
 #[cfg(feature = "serde")]
 pub use serde_json::{Map, Value};
-pub use ssql_macro::ORM;
+pub use ssql_macro::{ColumnGroup, SsqlEnum, ORM};
 pub use tiberius::{self, Client, ColumnData, IntoRow, IntoSql, QueryStream, Row, ToSql, TokenRow};
 pub use tokio::net::TcpStream;
 pub use tokio_util::compat::{Compat, TokioAsyncWriteCompatExt};
 
 pub use crate::error::custom_error::SsqlResult;
+pub use crate::structs::bulk_insert::BulkOptions;
+pub use crate::structs::column::Column;
+pub use crate::structs::column_group::ColumnGroup;
 pub use crate::structs::query_builder::QueryAble;
 pub use crate::structs::query_builder::QueryBuilderI;
+#[cfg(feature = "polars")]
+pub use crate::structs::raw_query_builder::UntypedRawQuery;
+pub use crate::structs::retry::RetryPolicy;
+pub use crate::structs::ssql_enum::SsqlEnum;
+pub use crate::structs::ssql_keyed::SsqlKeyed;
 pub use crate::structs::ssql_marker::SsqlMarker;
+pub use crate::structs::table_scope::TableScope;
+pub use crate::structs::tracked::Tracked;
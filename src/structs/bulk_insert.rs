@@ -0,0 +1,432 @@
+use tiberius::{Client, ColumnData, TokenRow};
+use tokio::net::TcpStream;
+use tokio_util::compat::Compat;
+
+use crate::structs::table_scope::TableScope;
+use crate::utils::quote_ident;
+use crate::{SsqlError, SsqlKeyed, SsqlMarker, SsqlResult};
+
+const STAGING_TABLE: &str = "#ssql_bulk_staging";
+
+/// Options for [`insert_many_with`], constructed with [`BulkOptions::new`] and chained setters.
+///
+/// tiberius does not accept hints for `INSERT BULK`, so when [`tablock`] or [`keep_identity`] is set,
+/// each batch is bulk loaded into a temp table first and moved into the target table with `INSERT ... SELECT`.
+/// Structs with `readonly` fields are always loaded this way, leaving those columns to their default.
+///
+/// Unlike a direct bulk load, which skips them, such a statement checks constraints, fires insert triggers
+/// and keeps null values instead of applying column defaults. Rows are also written twice,
+/// so a table lock does not get the minimal logging of a direct bulk load.
+///
+/// [`insert_many_with`]: trait.SsqlMarker.html#method.insert_many_with
+/// [`tablock`]: struct.BulkOptions.html#method.tablock
+/// [`keep_identity`]: struct.BulkOptions.html#method.keep_identity
+/// ```no_run
+/// # use ssql::prelude::*;
+/// let options = BulkOptions::new()
+///     .batch_size(100_000)
+///     .tablock()
+///     .on_progress(|p| println!("batch {} done, {} rows in total", p.batch, p.total_rows));
+/// ```
+#[derive(Default)]
+pub struct BulkOptions {
+    batch_size: Option<u64>,
+    tablock: bool,
+    keep_identity: bool,
+    scope: TableScope,
+    progress: Option<Box<dyn Fn(BulkProgress) + Send + Sync>>,
+}
+
+/// Progress of a bulk insert, reported after each committed batch.
+#[derive(Debug, Clone, Copy)]
+pub struct BulkProgress {
+    /// Index of the finished batch, starting from 1.
+    pub batch: u64,
+    /// Rows inserted by the finished batch.
+    pub batch_rows: u64,
+    /// Rows inserted so far, can be used to skip already inserted items when resuming a load.
+    pub total_rows: u64,
+}
+
+impl BulkOptions {
+    /// Default options, all rows are sent in one batch without any hint.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Commit every `size` rows instead of sending all rows in one request.
+    pub fn batch_size(mut self, size: u64) -> Self {
+        self.batch_size = Some(size.max(1));
+        self
+    }
+
+    /// Take a table lock for the duration of each batch, loading rows through a temp table.
+    pub fn tablock(mut self) -> Self {
+        self.tablock = true;
+        self
+    }
+
+    /// Insert identity columns with values provided by the struct instead of generated ones,
+    /// loading rows through a temp table.
+    pub fn keep_identity(mut self) -> Self {
+        self.keep_identity = true;
+        self
+    }
+
+    /// Insert into the table resolved in the given scope, see [`TableScope`].
+    ///
+    /// [`TableScope`]: struct.TableScope.html
+    pub fn scope(mut self, scope: TableScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Register a callback invoked after each committed batch.
+    pub fn on_progress<F>(mut self, func: F) -> Self
+    where
+        F: Fn(BulkProgress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Box::new(func));
+        self
+    }
+
+    fn staged(&self) -> bool {
+        self.tablock || self.keep_identity
+    }
+}
+
+pub(crate) async fn insert_many<T, I>(
+    iter: I,
+    options: &BulkOptions,
+    conn: &mut Client<Compat<TcpStream>>,
+) -> SsqlResult<u64>
+where
+    T: SsqlMarker,
+    I: IntoIterator<Item = T>,
+    I::IntoIter: Send,
+{
+    let table = options.scope.resolve(T::table_name());
+    let readonly = T::readonly_fields();
+    let mapping =
+        column_mapping(conn, &table, &T::fields(), &readonly, options.keep_identity).await?;
+    // `INSERT BULK` expects every writable column, readonly ones are left out by the explicit
+    // column list of the statement moving staged rows instead
+    let staged = options.staged() || !readonly.is_empty();
+    let columns = mapping
+        .iter()
+        .map(|(col, _)| quote_ident(col))
+        .collect::<Vec<_>>()
+        .join(",");
+    let target = match staged {
+        true => {
+            conn.simple_query(format!(
+                "DROP TABLE IF EXISTS {STAGING_TABLE}; \
+                SELECT TOP 0 {columns} INTO {STAGING_TABLE} FROM {table} \
+                UNION ALL SELECT TOP 0 {columns} FROM {table}"
+            ))
+            .await?
+            .into_results()
+            .await?;
+            STAGING_TABLE
+        }
+        false => table.as_str(),
+    };
+
+    let mut iter = iter.into_iter().peekable();
+    let mut progress = BulkProgress {
+        batch: 0,
+        batch_rows: 0,
+        total_rows: 0,
+    };
+    while iter.peek().is_some() {
+        let mut req = conn.bulk_insert(target).await?;
+        let mut sent = 0;
+        for item in iter.by_ref() {
+            let mut data: Vec<Option<ColumnData<'static>>> =
+                item.into_columns_data()?.into_iter().map(Some).collect();
+            let mut row = TokenRow::new();
+            for (_, idx) in mapping.iter() {
+                row.push(data[*idx].take().unwrap());
+            }
+            req.send(row).await?;
+            sent += 1;
+            if Some(sent) == options.batch_size {
+                break;
+            }
+        }
+        let mut inserted = req.finalize().await?.total();
+
+        if staged {
+            let lock = match options.tablock {
+                true => " WITH (TABLOCK)",
+                false => "",
+            };
+            let sql = format!(
+                "INSERT INTO {table}{lock} ({columns}) SELECT {columns} FROM {STAGING_TABLE}"
+            );
+            let sql = match options.keep_identity {
+                true => format!(
+                    "SET IDENTITY_INSERT {table} ON; {sql}; SET IDENTITY_INSERT {table} OFF"
+                ),
+                false => sql,
+            };
+            inserted = conn
+                .execute(sql.as_str(), &[])
+                .await
+                .map_err(|e| SsqlError::with_sql(e, &sql))?
+                .total();
+            conn.execute(format!("TRUNCATE TABLE {STAGING_TABLE}"), &[])
+                .await?;
+        }
+
+        progress.batch += 1;
+        progress.batch_rows = inserted;
+        progress.total_rows += inserted;
+        if let Some(func) = &options.progress {
+            func(progress);
+        }
+    }
+
+    if staged {
+        conn.execute(format!("DROP TABLE IF EXISTS {STAGING_TABLE}"), &[])
+            .await?;
+    }
+    Ok(progress.total_rows)
+}
+
+/// Error number thrown when rows of a versioned table were modified by another transaction.
+const CONFLICT_ERROR: u32 = 50001;
+
+pub(crate) async fn update_many<T, I>(
+    iter: I,
+    scope: &TableScope,
+    conn: &mut Client<Compat<TcpStream>>,
+) -> SsqlResult<u64>
+where
+    T: SsqlKeyed,
+    I: IntoIterator<Item = T>,
+    I::IntoIter: Send,
+{
+    let mut iter = iter.into_iter().peekable();
+    let version = match iter.peek() {
+        None => return Ok(0),
+        Some(item) => item.version().map(|v| v.0),
+    };
+    let table = scope.resolve(T::table_name());
+    let fields = T::fields();
+    let keys = key_columns::<T>(&fields, version);
+    let mut columns =
+        column_mapping(conn, &table, &fields, &T::readonly_fields(), true).await?;
+    columns.retain(|(_, idx)| !keys.iter().any(|(_, k)| k == idx));
+    if columns.is_empty() {
+        return Ok(0);
+    }
+    let set_clause = columns
+        .iter()
+        .map(|(col, _)| quote_ident(col))
+        .map(|col| format!("{col} = s.{col}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    columns.extend(keys.iter().map(|(col, idx)| (col.to_string(), *idx)));
+
+    let staged = stage(iter, &table, &columns, version, conn).await?;
+    let sql = format!(
+        "UPDATE t SET {set_clause} FROM {table} AS t INNER JOIN {STAGING_TABLE} AS s ON {}",
+        join_condition(&keys)
+    );
+    apply(sql, staged, version.is_some(), T::table_name(), conn).await
+}
+
+pub(crate) async fn delete_many<T, I>(
+    iter: I,
+    scope: &TableScope,
+    conn: &mut Client<Compat<TcpStream>>,
+) -> SsqlResult<u64>
+where
+    T: SsqlKeyed,
+    I: IntoIterator<Item = T>,
+    I::IntoIter: Send,
+{
+    let mut iter = iter.into_iter().peekable();
+    let version = match iter.peek() {
+        None => return Ok(0),
+        Some(item) => item.version().map(|v| v.0),
+    };
+    let table = scope.resolve(T::table_name());
+    let keys = key_columns::<T>(&T::fields(), version);
+    let columns: Vec<(String, usize)> = keys
+        .iter()
+        .map(|(col, idx)| (col.to_string(), *idx))
+        .collect();
+
+    let staged = stage(iter, &table, &columns, version, conn).await?;
+    let sql = format!(
+        "DELETE t FROM {table} AS t INNER JOIN {STAGING_TABLE} AS s ON {}",
+        join_condition(&keys)
+    );
+    apply(sql, staged, version.is_some(), T::table_name(), conn).await
+}
+
+/// Columns identifying a row: primary keys, plus the version column if present.
+fn key_columns<T: SsqlKeyed>(
+    fields: &[&'static str],
+    version: Option<&'static str>,
+) -> Vec<(&'static str, usize)> {
+    T::primary_key_fields()
+        .into_iter()
+        .chain(version)
+        .map(|key| (key, fields.iter().position(|f| *f == key).unwrap()))
+        .collect()
+}
+
+fn join_condition(keys: &[(&'static str, usize)]) -> String {
+    keys.iter()
+        .map(|(key, _)| quote_ident(key))
+        .map(|key| format!("t.{key} = s.{key}"))
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+/// Bulk load the given columns of all items into the staging table, returns number of staged rows.
+/// `table` is the quoted name of the table the staging table is shaped after.
+async fn stage<T, I>(
+    iter: I,
+    table: &str,
+    columns: &[(String, usize)],
+    version: Option<&'static str>,
+    conn: &mut Client<Compat<TcpStream>>,
+) -> SsqlResult<u64>
+where
+    T: SsqlMarker,
+    I: Iterator<Item = T> + Send,
+{
+    // rowversion columns can not be bulk loaded, stage them as plain binary
+    let select = columns
+        .iter()
+        .map(|(col, _)| match Some(col.as_str()) == version {
+            true => format!("CAST({0} AS BINARY(8)) AS {0}", quote_ident(col)),
+            false => quote_ident(col),
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    conn.simple_query(format!(
+        "DROP TABLE IF EXISTS {STAGING_TABLE}; \
+        SELECT TOP 0 {select} INTO {STAGING_TABLE} FROM {table} \
+        UNION ALL SELECT TOP 0 {select} FROM {table}"
+    ))
+    .await?
+    .into_results()
+    .await?;
+
+    let mut req = conn.bulk_insert(STAGING_TABLE).await?;
+    for item in iter {
+        let mut data: Vec<Option<ColumnData<'static>>> =
+            item.into_columns_data()?.into_iter().map(Some).collect();
+        let mut row = TokenRow::new();
+        for (_, idx) in columns.iter() {
+            row.push(data[*idx].take().unwrap());
+        }
+        req.send(row).await?;
+    }
+    Ok(req.finalize().await?.total())
+}
+
+/// Run a set based statement against the staging table and drop it afterwards.
+/// For versioned tables the statement is rolled back unless every staged row is affected,
+/// to a savepoint if a transaction is already open so the caller's transaction is kept.
+async fn apply(
+    sql: String,
+    staged: u64,
+    versioned: bool,
+    table: &str,
+    conn: &mut Client<Compat<TcpStream>>,
+) -> SsqlResult<u64> {
+    let sql = match versioned {
+        true => format!(
+            "DECLARE @ssql_outer INT = @@TRANCOUNT; \
+            IF @ssql_outer > 0 SAVE TRANSACTION ssql_apply ELSE BEGIN TRANSACTION; \
+            {sql}; \
+            IF @@ROWCOUNT <> {staged} BEGIN \
+            IF @ssql_outer > 0 ROLLBACK TRANSACTION ssql_apply ELSE ROLLBACK; \
+            THROW {CONFLICT_ERROR}, 'concurrency conflict', 1; END \
+            IF @ssql_outer = 0 COMMIT"
+        ),
+        false => sql,
+    };
+    let ret = conn.execute(sql.as_str(), &[]).await;
+    conn.execute(format!("DROP TABLE IF EXISTS {STAGING_TABLE}"), &[])
+        .await?;
+    match ret {
+        Ok(ret) => Ok(ret.total()),
+        Err(tiberius::error::Error::Server(e)) if e.code() == CONFLICT_ERROR => {
+            Err(SsqlError::ConcurrencyConflict(table.to_string()))
+        }
+        Err(e) => Err(SsqlError::with_sql(e, &sql)),
+    }
+}
+
+/// Resolve the columns a bulk insert writes to, in the column order of the database.
+///
+/// `INSERT BULK` expects values for every writable column of the table in that order,
+/// identity (unless `keep_identity` is set), computed and rowversion columns are excluded.
+/// Columns of fields marked `readonly` are excluded as well, so the rows must be loaded
+/// into a table with these columns only, such as the staging table.
+/// `table` is the quoted name of the table.
+/// Returns the column names along with indexes into `fields`, or an error listing every column
+/// that can not be matched.
+pub(crate) async fn column_mapping(
+    conn: &mut Client<Compat<TcpStream>>,
+    table: &str,
+    fields: &[&'static str],
+    readonly: &[&'static str],
+    keep_identity: bool,
+) -> SsqlResult<Vec<(String, usize)>> {
+    let rows = conn
+        .query(
+            "SELECT name, CAST(CASE WHEN (is_identity = 1 AND @P2 = 0) OR is_computed = 1 \
+            OR system_type_id = 189 THEN 0 ELSE 1 END AS BIT) AS insertable \
+            FROM sys.columns WHERE object_id = OBJECT_ID(@P1) ORDER BY column_id",
+            &[&table, &keep_identity],
+        )
+        .await?
+        .into_first_result()
+        .await?;
+    if rows.is_empty() {
+        return Err(format!("table {} not found or has no columns", table).into());
+    }
+
+    let mut mapping = vec![];
+    let mut matched = vec![false; fields.len()];
+    let mut missing_fields = vec![];
+    for row in rows.iter() {
+        let column = row.get::<&str, _>(0).unwrap_or_default();
+        let insertable = row.get::<bool, _>(1).unwrap_or(true);
+        let idx = fields.iter().position(|f| f.eq_ignore_ascii_case(column));
+        if let Some(idx) = idx {
+            matched[idx] = true;
+        }
+        match (insertable, idx) {
+            (false, _) => {}
+            (true, Some(idx)) if readonly.contains(&fields[idx]) => {}
+            (true, Some(idx)) => mapping.push((column.to_string(), idx)),
+            (true, None) => missing_fields.push(column.to_string()),
+        }
+    }
+    let unknown_columns: Vec<&str> = fields
+        .iter()
+        .zip(matched)
+        .filter(|(_, m)| !m)
+        .map(|(f, _)| *f)
+        .collect();
+
+    match (missing_fields.is_empty(), unknown_columns.is_empty()) {
+        (true, true) => Ok(mapping),
+        _ => Err(format!(
+            "bulk insert into {} failed, columns without corresponding field: [{}], fields without corresponding column: [{}]",
+            table,
+            missing_fields.join(", "),
+            unknown_columns.join(", ")
+        )
+        .into()),
+    }
+}
@@ -0,0 +1,190 @@
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+
+use tiberius::ToSql;
+
+use crate::structs::filter::{ColExpr, FilterExpr};
+use crate::structs::ssql_marker::SsqlMarker;
+
+/// Typed handle to a column of table struct `T` holding values of type `V`,
+/// generated by `#[derive(ORM)]` as associated constant named after the field in upper case.
+/// Unlike [`SsqlMarker::col`] a misspelled column fails to compile,
+/// and filter values are checked against the type of the field, see [`ColumnValue`].
+///
+/// Flattened fields have no constant, use [`SsqlMarker::col`] with the name of the column instead.
+///
+/// [`SsqlMarker::col`]: trait.SsqlMarker.html#method.col
+/// [`ColumnValue`]: trait.ColumnValue.html
+/// ```no_run
+/// # use ssql::prelude::*;
+/// #[derive(ORM)]
+/// #[ssql(table = person)]
+/// struct Person {
+///     #[ssql(primary_key)]
+///     id: i32,
+///     email: Option<String>,
+/// }
+///
+/// # fn _test() -> SsqlResult<()> {
+/// let query = Person::query()
+///     .filter(Person::ID.gt(&3))?
+///     .filter(Person::EMAIL.contains("gmail"))?
+///     .order_by_asc(Person::EMAIL)?;
+/// # Ok(())
+/// # }
+/// ```
+/// Values of another type are rejected at compile time.
+/// ```compile_fail
+/// # use ssql::prelude::*;
+/// # #[derive(ORM)]
+/// # #[ssql(table = person)]
+/// # struct Person {
+/// #     #[ssql(primary_key)]
+/// #     id: i32,
+/// # }
+/// let filter = Person::ID.eq(&"abc");
+/// ```
+pub struct Column<T, V> {
+    field: &'static str,
+    _marker: PhantomData<fn() -> (T, V)>,
+}
+
+impl<T, V> Column<T, V> {
+    #[doc(hidden)]
+    pub const fn new(field: &'static str) -> Self {
+        Self {
+            field,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Name of the column.
+    pub fn name(&self) -> &'static str {
+        self.field
+    }
+}
+
+impl<T, V> Clone for Column<T, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, V> Copy for Column<T, V> {}
+
+impl<T, V> Debug for Column<T, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Column").field(&self.field).finish()
+    }
+}
+
+impl<T: SsqlMarker, V> From<Column<T, V>> for ColExpr {
+    fn from(column: Column<T, V>) -> Self {
+        column.expr()
+    }
+}
+
+impl<T: SsqlMarker, V> Column<T, V> {
+    /// Untyped [`ColExpr`] of this column, e.g. for [`update_fields`] or filters not provided here.
+    ///
+    /// [`ColExpr`]: struct.ColExpr.html
+    /// [`update_fields`]: trait.SsqlKeyed.html#method.update_fields
+    pub fn expr(&self) -> ColExpr {
+        ColExpr::new(T::table_name(), self.field)
+    }
+
+    /// Same as [`ColExpr::eq`](struct.ColExpr.html#method.eq).
+    pub fn eq<'b, W: ColumnValue<V> + ToSql>(&self, other: &'b W) -> FilterExpr<'b> {
+        self.expr().eq(other)
+    }
+
+    /// Same as [`ColExpr::neq`](struct.ColExpr.html#method.neq).
+    pub fn neq<'b, W: ColumnValue<V> + ToSql>(&self, other: &'b W) -> FilterExpr<'b> {
+        self.expr().neq(other)
+    }
+
+    /// Same as [`ColExpr::lt`](struct.ColExpr.html#method.lt).
+    pub fn lt<'b, W: ColumnValue<V> + ToSql>(&self, other: &'b W) -> FilterExpr<'b> {
+        self.expr().lt(other)
+    }
+
+    /// Same as [`ColExpr::lt_eq`](struct.ColExpr.html#method.lt_eq).
+    pub fn lt_eq<'b, W: ColumnValue<V> + ToSql>(&self, other: &'b W) -> FilterExpr<'b> {
+        self.expr().lt_eq(other)
+    }
+
+    /// Same as [`ColExpr::gt`](struct.ColExpr.html#method.gt).
+    pub fn gt<'b, W: ColumnValue<V> + ToSql>(&self, other: &'b W) -> FilterExpr<'b> {
+        self.expr().gt(other)
+    }
+
+    /// Same as [`ColExpr::gt_eq`](struct.ColExpr.html#method.gt_eq).
+    pub fn gt_eq<'b, W: ColumnValue<V> + ToSql>(&self, other: &'b W) -> FilterExpr<'b> {
+        self.expr().gt_eq(other)
+    }
+
+    /// Same as [`ColExpr::is_in`](struct.ColExpr.html#method.is_in).
+    pub fn is_in<'b, W: ColumnValue<V> + ToSql>(&self, ls: &'b [W]) -> FilterExpr<'b> {
+        self.expr().is_in(ls)
+    }
+
+    /// Same as [`ColExpr::between`](struct.ColExpr.html#method.between).
+    pub fn between<'b, W: ColumnValue<V> + ToSql>(&self, start: &'b W, end: &'b W) -> FilterExpr<'b> {
+        self.expr().between(start, end)
+    }
+}
+
+impl<T: SsqlMarker, V> Column<T, Option<V>> {
+    /// Same as [`ColExpr::is_null`](struct.ColExpr.html#method.is_null), only for nullable fields.
+    pub fn is_null<'b>(&self) -> FilterExpr<'b> {
+        self.expr().is_null()
+    }
+
+    /// Same as [`ColExpr::is_not_null`](struct.ColExpr.html#method.is_not_null), only for nullable fields.
+    pub fn is_not_null<'b>(&self) -> FilterExpr<'b> {
+        self.expr().is_not_null()
+    }
+}
+
+impl<T: SsqlMarker, V: TextColumn> Column<T, V> {
+    /// Same as [`ColExpr::contains`](struct.ColExpr.html#method.contains), only for text fields.
+    pub fn contains<'b>(&self, other: &'b str) -> FilterExpr<'b> {
+        self.expr().contains(other)
+    }
+
+    /// Same as [`ColExpr::startswith`](struct.ColExpr.html#method.startswith), only for text fields.
+    pub fn startswith<'b>(&self, other: &'b str) -> FilterExpr<'b> {
+        self.expr().startswith(other)
+    }
+
+    /// Same as [`ColExpr::endswith`](struct.ColExpr.html#method.endswith), only for text fields.
+    pub fn endswith<'b>(&self, other: &'b str) -> FilterExpr<'b> {
+        self.expr().endswith(other)
+    }
+}
+
+/// Marks types of values that can be compared with a [`Column`] of type `V`:
+/// `V` itself, the inner type of a nullable column, and `&str`/`&[u8]` for text and binary columns.
+///
+/// [`Column`]: struct.Column.html
+pub trait ColumnValue<V> {}
+
+impl<V: ToSql> ColumnValue<V> for V {}
+
+impl<V: ToSql> ColumnValue<Option<V>> for V {}
+
+impl ColumnValue<String> for &str {}
+
+impl ColumnValue<Option<String>> for &str {}
+
+impl ColumnValue<Vec<u8>> for &[u8] {}
+
+impl ColumnValue<Option<Vec<u8>>> for &[u8] {}
+
+/// Field types supporting pattern matching filters like `contains`.
+#[doc(hidden)]
+pub trait TextColumn {}
+
+impl TextColumn for String {}
+
+impl TextColumn for Option<String> {}
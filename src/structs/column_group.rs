@@ -0,0 +1,65 @@
+use tiberius::{ColumnData, Row};
+
+use crate::SsqlResult;
+
+/// A trait automatically derived via `#[derive(ColumnGroup)]` macro, for a group of columns
+/// repeated across tables, such as the parts of an address.
+/// A field of such type marked `#[ssql(flatten)]` in a struct deriving `ORM` is stored as one column per field
+/// of the group, named with the field name and `_` as prefix, or the prefix given by `#[ssql(prefix = "...")]`.
+///
+/// Fields of the group support `rename`, `skip`, `with` and `json` attributes.
+/// In [`json`] results the group is output as nested object, thus it needs to implement `Serialize`
+/// if feature `serde` is enabled.
+///
+/// [`json`]: trait.QueryAble.html#method.json
+/// ```
+/// # use ssql::prelude::*;
+/// # use serde::Serialize;
+/// #[derive(ColumnGroup, Serialize)]
+/// struct Address {
+///     street: String,
+///     city: Option<String>,
+/// }
+///
+/// #[derive(ORM)]
+/// #[ssql(table = person)]
+/// struct Person {
+///     #[ssql(primary_key)]
+///     id: i32,
+///     // columns address_street, address_city
+///     #[ssql(flatten)]
+///     address: Address,
+///     // columns billing_street, billing_city
+///     #[ssql(flatten, prefix = "billing_")]
+///     billing_address: Address,
+/// }
+///
+/// assert!(Person::fields().contains(&"billing_city"));
+/// # fn _test() -> SsqlResult<()> {
+/// let query = Person::query().filter(Person::col("address_city")?.eq(&"Berlin"))?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait ColumnGroup: Sized + Send + Sync {
+    #[doc(hidden)]
+    fn columns() -> Vec<&'static str>;
+
+    #[doc(hidden)]
+    fn column_values(&self) -> SsqlResult<Vec<ColumnData<'_>>>;
+
+    #[doc(hidden)]
+    fn into_column_values(self) -> SsqlResult<Vec<ColumnData<'static>>>;
+
+    // `prefix` includes the table name when the group is loaded by a query builder
+    #[doc(hidden)]
+    fn from_row(row: &Row, table: &str, prefix: &str) -> SsqlResult<Self>;
+
+    /// Column names with the given prefix, leaked once per struct as field names are static.
+    #[doc(hidden)]
+    fn prefixed_columns(prefix: &str) -> Vec<&'static str> {
+        Self::columns()
+            .into_iter()
+            .map(|c| &*Box::leak(format!("{}{}", prefix, c).into_boxed_str()))
+            .collect()
+    }
+}
@@ -0,0 +1,175 @@
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use polars::prelude::*;
+use tiberius::numeric::Numeric;
+use tiberius::{Column, ColumnData, ColumnType, FromSqlOwned, Uuid};
+
+use crate::utils::{column_text, is_null};
+use crate::SsqlResult;
+
+/// Type of a field that can be turned into a column of a DataFrame,
+/// used by the `df` method generated with `#[derive(ORM)]`.
+///
+/// Temporal values are stored with microsecond precision, `Numeric` as a decimal
+/// with the largest scale of the column and `Uuid` as its text.
+#[doc(hidden)]
+pub trait ToSeries: Sized {
+    fn to_series(name: &str, values: Vec<Option<Self>>) -> PolarsResult<Series>;
+}
+
+macro_rules! impl_to_series {
+    ($($ty:ty),*) => {
+        $(
+            impl ToSeries for $ty {
+                fn to_series(name: &str, values: Vec<Option<Self>>) -> PolarsResult<Series> {
+                    Ok(Series::new(name, values))
+                }
+            }
+        )*
+    };
+}
+
+impl_to_series!(bool, u8, i16, i32, i64, f32, f64, String);
+
+impl ToSeries for Vec<u8> {
+    fn to_series(name: &str, values: Vec<Option<Self>>) -> PolarsResult<Series> {
+        Ok(BinaryChunked::from_iter_options(name, values.into_iter()).into_series())
+    }
+}
+
+impl ToSeries for Uuid {
+    fn to_series(name: &str, values: Vec<Option<Self>>) -> PolarsResult<Series> {
+        let values: Vec<Option<String>> = values
+            .into_iter()
+            .map(|v| v.map(|v| v.to_string()))
+            .collect();
+        Ok(Series::new(name, values))
+    }
+}
+
+impl ToSeries for Numeric {
+    fn to_series(name: &str, values: Vec<Option<Self>>) -> PolarsResult<Series> {
+        // values of a column share its scale, unless they come from an expression
+        let scale = values.iter().flatten().map(|v| v.scale()).max().unwrap_or(0);
+        let values = values.into_iter().map(|v| {
+            v.map(|v| v.value() * 10i128.pow((scale - v.scale()) as u32))
+        });
+        Ok(Int128Chunked::from_iter_options(name, values)
+            .into_decimal(None, scale as usize)?
+            .into_series())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl ToSeries for NaiveDateTime {
+    fn to_series(name: &str, values: Vec<Option<Self>>) -> PolarsResult<Series> {
+        let values = values.into_iter().map(|v| v.map(|v| v.and_utc().timestamp_micros()));
+        Ok(Int64Chunked::from_iter_options(name, values)
+            .into_datetime(TimeUnit::Microseconds, None)
+            .into_series())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl ToSeries for DateTime<Utc> {
+    fn to_series(name: &str, values: Vec<Option<Self>>) -> PolarsResult<Series> {
+        let values = values.into_iter().map(|v| v.map(|v| v.timestamp_micros()));
+        Ok(Int64Chunked::from_iter_options(name, values)
+            .into_datetime(TimeUnit::Microseconds, Some("UTC".to_string()))
+            .into_series())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl ToSeries for NaiveDate {
+    fn to_series(name: &str, values: Vec<Option<Self>>) -> PolarsResult<Series> {
+        let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+        let values = values
+            .into_iter()
+            .map(|v| v.map(|v| v.signed_duration_since(epoch).num_days() as i32));
+        Ok(Int32Chunked::from_iter_options(name, values)
+            .into_date()
+            .into_series())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl ToSeries for NaiveTime {
+    fn to_series(name: &str, values: Vec<Option<Self>>) -> PolarsResult<Series> {
+        // nanoseconds since midnight
+        let values = values.into_iter().map(|v| {
+            v.map(|v| v.num_seconds_from_midnight() as i64 * 1_000_000_000 + v.nanosecond() as i64)
+        });
+        Ok(Int64Chunked::from_iter_options(name, values)
+            .into_time()
+            .into_series())
+    }
+}
+
+/// Column of a DataFrame holding the values of a column of a result set,
+/// its dtype inferred from the SQL type of the column.
+pub(crate) fn column_series(column: &Column, values: Vec<ColumnData<'static>>) -> SsqlResult<Series> {
+    let name = column.name();
+    // variable sized types are told apart by the values
+    let first = values.iter().find(|v| !is_null(v));
+    match column.column_type() {
+        ColumnType::Bit | ColumnType::Bitn => decode::<bool>(name, values),
+        ColumnType::Int1 => decode::<u8>(name, values),
+        ColumnType::Int2 => decode::<i16>(name, values),
+        ColumnType::Int4 => decode::<i32>(name, values),
+        ColumnType::Int8 => decode::<i64>(name, values),
+        ColumnType::Intn => match first {
+            Some(ColumnData::U8(_)) => decode::<u8>(name, values),
+            Some(ColumnData::I16(_)) => decode::<i16>(name, values),
+            Some(ColumnData::I32(_)) => decode::<i32>(name, values),
+            _ => decode::<i64>(name, values),
+        },
+        ColumnType::Float4
+        | ColumnType::Float8
+        | ColumnType::Floatn
+        | ColumnType::Money
+        | ColumnType::Money4 => match first {
+            Some(ColumnData::F32(_)) => decode::<f32>(name, values),
+            _ => decode::<f64>(name, values),
+        },
+        ColumnType::Decimaln | ColumnType::Numericn => decode::<Numeric>(name, values),
+        ColumnType::Guid => decode::<Uuid>(name, values),
+        ColumnType::BigVarBin | ColumnType::BigBinary | ColumnType::Image => {
+            decode::<Vec<u8>>(name, values)
+        }
+        ColumnType::BigVarChar
+        | ColumnType::BigChar
+        | ColumnType::NVarchar
+        | ColumnType::NChar
+        | ColumnType::Text
+        | ColumnType::NText => decode::<String>(name, values),
+        #[cfg(feature = "chrono")]
+        ColumnType::Datetime
+        | ColumnType::Datetime4
+        | ColumnType::Datetimen
+        | ColumnType::Datetime2 => decode::<NaiveDateTime>(name, values),
+        #[cfg(feature = "chrono")]
+        ColumnType::DatetimeOffsetn => decode::<DateTime<Utc>>(name, values),
+        #[cfg(feature = "chrono")]
+        ColumnType::Daten => decode::<NaiveDate>(name, values),
+        #[cfg(feature = "chrono")]
+        ColumnType::Timen => decode::<NaiveTime>(name, values),
+        // xml, variants, user-defined types and dates without feature `chrono`
+        _ => {
+            let values: Vec<Option<String>> = values.iter().map(column_text).collect();
+            Ok(Series::new(name, values))
+        }
+    }
+}
+
+fn decode<T: FromSqlOwned + ToSeries>(name: &str, values: Vec<ColumnData<'static>>) -> SsqlResult<Series> {
+    // null values may be of another variant than the values of the column
+    let values = values
+        .into_iter()
+        .map(|v| match is_null(&v) {
+            true => Ok(None),
+            false => T::from_sql_owned(v),
+        })
+        .collect::<tiberius::Result<Vec<_>>>()?;
+    Ok(T::to_series(name, values)?)
+}
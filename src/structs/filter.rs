@@ -1,12 +1,74 @@
-use tiberius::ToSql;
+use tiberius::{ColumnData, ToSql};
+
+use crate::structs::query_core::{Param, PARAM_LIMIT};
+use crate::utils::quote_ident;
+use crate::SsqlResult;
 
 /// Column Expression
 pub struct ColExpr {
     pub(crate) table: &'static str,
     pub(crate) field: &'static str,
+    // path into a JSON column, the expression then stands for `JSON_VALUE(column, path)`
+    pub(crate) json_path: Option<String>,
 }
 
 impl ColExpr {
+    pub(crate) fn new(table: &'static str, field: &'static str) -> Self {
+        Self {
+            table,
+            field,
+            json_path: None,
+        }
+    }
+
+    /// refer to a scalar value inside a JSON column, filtering and ordering on `JSON_VALUE(column, path)`.
+    /// The same can be written as `Person::col("payload.$.customer.id")`.
+    /// `JSON_VALUE` returns text, SQL Server converts it when compared with a number.
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    /// # #[derive(ORM)]
+    /// # #[ssql(table = person)]
+    /// # struct Person{
+    /// #    id: i32,
+    /// #    payload: String,
+    /// # }
+    /// # fn _test() -> SsqlResult<()> {
+    /// let query = Person::query().filter(
+    ///     Person::col("payload")?.json("$.customer.id").eq(&5)
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// SQL: `... WHERE JSON_VALUE(person.payload, '$.customer.id') = 5`
+    pub fn json(mut self, path: &str) -> Self {
+        self.json_path = Some(path.to_string());
+        self
+    }
+
+    /// generate filter expression checking whether a JSON array contains a value, expanding it with `OPENJSON`.
+    /// Without a path set by [`json`] the column itself holds the array.
+    ///
+    /// [`json`]: #method.json
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    /// # #[derive(ORM)]
+    /// # #[ssql(table = person)]
+    /// # struct Person{
+    /// #    id: i32,
+    /// #    payload: String,
+    /// # }
+    /// # fn _test() -> SsqlResult<()> {
+    /// let query = Person::query().filter(
+    ///     Person::col("payload.$.tags")?.json_contains(&"vip")
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// SQL: `... WHERE EXISTS (SELECT 1 FROM OPENJSON(person.payload, '$.tags') WHERE value = 'vip')`
+    pub fn json_contains(self, other: &dyn ToSql) -> FilterExpr {
+        self.expr_wrapper(ConditionVar::JsonContains(other))
+    }
+
     /// generate filter expression checking whether this column equals to a value.
     /// ```no_run
     /// # use ssql::prelude::*;
@@ -271,7 +333,14 @@ impl ColExpr {
     }
 
     pub(crate) fn full_column_name(&self) -> String {
-        format!("{}.{}", self.table, self.field)
+        match &self.json_path {
+            Some(path) => format!("JSON_VALUE({}, {})", self.column_name(), sql_literal(path)),
+            None => self.column_name(),
+        }
+    }
+
+    fn column_name(&self) -> String {
+        format!("{}.{}", quote_ident(self.table), quote_ident(self.field))
     }
 }
 
@@ -285,34 +354,40 @@ pub struct FilterExpr<'b> {
 }
 
 impl<'b> FilterExpr<'b> {
-    pub(crate) fn to_sql(&self, idx: &mut i32, query_params: &mut Vec<&'b dyn ToSql>) -> String {
+    pub(crate) fn to_sql(
+        &self,
+        idx: &mut i32,
+        query_params: &mut Vec<Param<'b>>,
+        strategy: InListStrategy,
+    ) -> SsqlResult<String> {
         match self.or_cons.is_empty() {
-            true => self.to_sql_wrapper(idx, query_params),
+            true => self.to_sql_wrapper(idx, query_params, strategy),
             false => {
                 let tmp = self
                     .or_cons
                     .iter()
                     .chain([self])
-                    .map(|x| x.to_sql_wrapper(idx, query_params))
-                    .reduce(|cur, nxt| format!("{cur} OR {nxt}"))
-                    .unwrap();
-                format!("( {} )", tmp)
+                    .map(|x| x.to_sql_wrapper(idx, query_params, strategy))
+                    .collect::<SsqlResult<Vec<_>>>()?
+                    .join(" OR ");
+                Ok(format!("( {} )", tmp))
             }
         }
     }
     pub(crate) fn to_sql_wrapper(
         &self,
         idx: &mut i32,
-        query_params: &mut Vec<&'b dyn ToSql>,
-    ) -> String {
-        match &self.con {
+        query_params: &mut Vec<Param<'b>>,
+        strategy: InListStrategy,
+    ) -> SsqlResult<String> {
+        let sql = match &self.con {
             ConditionVar::Eq(v)
             | ConditionVar::Neq(v)
             | ConditionVar::Gt(v)
             | ConditionVar::GtEq(v)
             | ConditionVar::Lt(v)
             | ConditionVar::LtEq(v) => {
-                query_params.push(*v);
+                query_params.push(Param::Ref(*v));
                 *idx += 1;
                 format!(
                     " {} {} @p{}",
@@ -331,6 +406,38 @@ impl<'b> FilterExpr<'b> {
             ConditionVar::Contains(v) => {
                 format!("{} LIKE '%{}%' ", self.col.full_column_name(), v)
             }
+            ConditionVar::IsIn(v) if *idx as usize + v.len() > PARAM_LIMIT => {
+                let expanded = match strategy {
+                    InListStrategy::OpenJson => {
+                        let values = v
+                            .iter()
+                            .map(|x| to_text(*x).map(|t| json_string(&t)))
+                            .collect::<SsqlResult<Vec<_>>>()?;
+                        query_params.push(Param::Owned(format!("[{}]", values.join(","))));
+                        *idx += 1;
+                        format!("SELECT value FROM OPENJSON(@p{})", idx)
+                    }
+                    InListStrategy::StringSplit => {
+                        let values = v.iter().map(|x| to_text(*x)).collect::<SsqlResult<Vec<_>>>()?;
+                        if values.iter().any(|x| x.contains(',')) {
+                            return Err("values containing ',' can not be expanded with STRING_SPLIT".into());
+                        }
+                        query_params.push(Param::Owned(values.join(",")));
+                        *idx += 1;
+                        format!("SELECT value FROM STRING_SPLIT(@p{}, ',')", idx)
+                    }
+                    InListStrategy::Params => {
+                        return Err(format!(
+                            "IN filter on {} has {} values, exceeding the parameter limit of {}",
+                            self.col.full_column_name(),
+                            v.len(),
+                            PARAM_LIMIT
+                        )
+                        .into())
+                    }
+                };
+                format!("{} IN ({})", self.col.full_column_name(), expanded)
+            }
             ConditionVar::IsIn(v) => {
                 let mut i = *idx;
                 *idx += v.len() as i32;
@@ -342,13 +449,13 @@ impl<'b> FilterExpr<'b> {
                     })
                     .reduce(|cur, nxt| format!("{},{}", cur, nxt))
                     .unwrap();
-                query_params.extend(v);
+                query_params.extend(v.iter().map(|x| Param::Ref(*x)));
                 format!("{} IN ({})", self.col.full_column_name(), cond_params)
             }
             ConditionVar::Between((v1, v2)) => {
                 *idx += 2;
-                query_params.push(*v1);
-                query_params.push(*v2);
+                query_params.push(Param::Ref(*v1));
+                query_params.push(Param::Ref(*v2));
                 format!(
                     "{} BETWEEN @p{} AND @p{}",
                     self.col.full_column_name(),
@@ -356,13 +463,26 @@ impl<'b> FilterExpr<'b> {
                     idx
                 )
             }
+            ConditionVar::JsonContains(v) => {
+                query_params.push(Param::Ref(*v));
+                *idx += 1;
+                let source = match &self.col.json_path {
+                    Some(path) => format!("{}, {}", self.col.column_name(), sql_literal(path)),
+                    None => self.col.column_name(),
+                };
+                format!(
+                    "EXISTS (SELECT 1 FROM OPENJSON({}) WHERE [value] = @p{})",
+                    source, idx
+                )
+            }
             ConditionVar::StarsWith(v) => {
                 format!("{} LIKE '{}%' ", self.col.full_column_name(), v)
             }
             ConditionVar::EndsWith(v) => {
                 format!("{} LIKE '%{}' ", self.col.full_column_name(), v)
             }
-        }
+        };
+        Ok(sql)
     }
 
     /// supplement 'or' filters for current filter statement.
@@ -389,6 +509,62 @@ impl<'b> FilterExpr<'b> {
     }
 }
 
+/// Strategy used when the values of an [`is_in`] filter exceed the parameter limit of SQL Server,
+/// 2098 parameters per query as `sp_executesql` takes 2 of the 2100 parameters of a request.
+/// Lists under the limit are always bound with one parameter per value.
+/// tiberius does not support table-valued parameters, so the list is sent as one string parameter instead.
+///
+/// [`is_in`]: struct.ColExpr.html#method.is_in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InListStrategy {
+    /// Send values as one JSON array and expand it with `OPENJSON`, requires compatibility level 130.
+    #[default]
+    OpenJson,
+    /// Send values as one comma separated string and expand it with `STRING_SPLIT`,
+    /// values must not contain commas.
+    StringSplit,
+    /// Keep one parameter per value and return an error if the limit is exceeded.
+    Params,
+}
+
+/// Render a parameter as text to be expanded on server side.
+fn to_text(value: &dyn ToSql) -> SsqlResult<String> {
+    let text = match value.to_sql() {
+        ColumnData::U8(v) => v.map(|v| v.to_string()),
+        ColumnData::I16(v) => v.map(|v| v.to_string()),
+        ColumnData::I32(v) => v.map(|v| v.to_string()),
+        ColumnData::I64(v) => v.map(|v| v.to_string()),
+        ColumnData::F32(v) => v.map(|v| v.to_string()),
+        ColumnData::F64(v) => v.map(|v| v.to_string()),
+        ColumnData::Bit(v) => v.map(|v| (v as u8).to_string()),
+        ColumnData::String(v) => v.map(|v| v.to_string()),
+        ColumnData::Guid(v) => v.map(|v| v.to_string()),
+        ColumnData::Numeric(v) => v.map(|v| v.to_string()),
+        _ => return Err("only numeric, string and guid values can be expanded in a large IN filter".into()),
+    };
+    text.ok_or_else(|| "NULL can not be used in an IN filter".into())
+}
+
+/// Quote a string as sql literal, used for JSON paths which must be literals before SQL Server 2017.
+fn sql_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn json_string(value: &str) -> String {
+    let mut ret = String::with_capacity(value.len() + 2);
+    ret.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            c if (c as u32) < 0x20 => ret.push_str(&format!("\\u{:04x}", c as u32)),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
 enum ConditionVar<'a> {
     Eq(&'a dyn ToSql),
     Neq(&'a dyn ToSql),
@@ -403,6 +579,7 @@ enum ConditionVar<'a> {
     StarsWith(&'a str),
     EndsWith(&'a str),
     Between((&'a dyn ToSql, &'a dyn ToSql)),
+    JsonContains(&'a dyn ToSql),
 }
 
 impl<'a> ConditionVar<'a> {
@@ -421,6 +598,7 @@ impl<'a> ConditionVar<'a> {
             ConditionVar::Between(_) => "",
             ConditionVar::StarsWith(_) => "",
             ConditionVar::EndsWith(_) => "",
+            ConditionVar::JsonContains(_) => "",
         }
     }
 }
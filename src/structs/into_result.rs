@@ -7,11 +7,10 @@ use tiberius::QueryStream;
 use tiberius::Row;
 
 use crate::structs::ssql_marker::SsqlMarker;
-#[cfg(feature = "polars")]
 use crate::SsqlResult;
 
 pub trait IntoResult {
-    fn to_struct(r: &Row) -> Self
+    fn to_struct(r: &Row) -> SsqlResult<Self>
     where
         Self: Sized + 'static;
 
@@ -19,7 +18,7 @@ pub trait IntoResult {
     type Js: Send + Sync;
 
     #[cfg(feature = "serde")]
-    fn to_json(r: &Row) -> Self::Js
+    fn to_json(r: &Row) -> SsqlResult<Self::Js>
     where
         Self: Sized;
 
@@ -36,7 +35,7 @@ impl<Ta> IntoResult for Ta
 where
     Ta: SsqlMarker,
 {
-    fn to_struct(r: &Row) -> Self
+    fn to_struct(r: &Row) -> SsqlResult<Self>
     where
         Self: Sized + 'static,
     {
@@ -46,11 +45,11 @@ where
     #[cfg(feature = "serde")]
     type Js = Value;
     #[cfg(feature = "serde")]
-    fn to_json(r: &Row) -> Value
+    fn to_json(r: &Row) -> SsqlResult<Value>
     where
         Self: Sized,
     {
-        Ta::row_to_json(r).into()
+        Ok(Ta::row_to_json(r)?.into())
     }
 
     #[cfg(feature = "polars")]
@@ -70,33 +69,37 @@ where
     Ta: SsqlMarker,
     Tb: SsqlMarker,
 {
-    fn to_struct(r: &Row) -> Self
+    fn to_struct(r: &Row) -> SsqlResult<Self>
     where
         Self: Sized + 'static,
     {
-        (Ta::row_to_struct(r), Tb::row_to_struct(r))
+        Ok((Ta::row_to_struct(r)?, Tb::row_to_struct(r)?))
     }
 
     #[cfg(feature = "serde")]
     type Js = (Value, Value);
 
     #[cfg(feature = "serde")]
-    fn to_json(r: &Row) -> Self::Js
+    fn to_json(r: &Row) -> SsqlResult<Self::Js>
     where
         Self: Sized,
     {
-        (Ta::row_to_json(r).into(), Tb::row_to_json(r).into())
+        Ok((Ta::row_to_json(r)?.into(), Tb::row_to_json(r)?.into()))
     }
 
     #[cfg(feature = "polars")]
-    type Df = ();
+    type Df = (DataFrame, DataFrame);
 
     #[cfg(feature = "polars")]
     async fn df(v: QueryStream<'_>) -> SsqlResult<Self::Df>
     where
         Self: Sized,
     {
-        todo!()
+        let rows = v.into_first_result().await?;
+        Ok((
+            Ta::dataframe_from_rows(&rows)?,
+            Tb::dataframe_from_rows(&rows)?,
+        ))
     }
 }
 
@@ -106,41 +109,46 @@ where
     Tb: SsqlMarker,
     Tc: SsqlMarker,
 {
-    fn to_struct(r: &Row) -> Self
+    fn to_struct(r: &Row) -> SsqlResult<Self>
     where
         Self: Sized + 'static,
     {
-        (
-            Ta::row_to_struct(r),
-            Tb::row_to_struct(r),
-            Tc::row_to_struct(r),
-        )
+        Ok((
+            Ta::row_to_struct(r)?,
+            Tb::row_to_struct(r)?,
+            Tc::row_to_struct(r)?,
+        ))
     }
 
     #[cfg(feature = "serde")]
     type Js = (Value, Value, Value);
 
     #[cfg(feature = "serde")]
-    fn to_json(r: &Row) -> Self::Js
+    fn to_json(r: &Row) -> SsqlResult<Self::Js>
     where
         Self: Sized,
     {
-        (
-            Ta::row_to_json(r).into(),
-            Tb::row_to_json(r).into(),
-            Tc::row_to_json(r).into(),
-        )
+        Ok((
+            Ta::row_to_json(r)?.into(),
+            Tb::row_to_json(r)?.into(),
+            Tc::row_to_json(r)?.into(),
+        ))
     }
 
     #[cfg(feature = "polars")]
-    type Df = ();
+    type Df = (DataFrame, DataFrame, DataFrame);
 
     #[cfg(feature = "polars")]
-    async fn df(v: QueryStream<'_>) -> SsqlResult<()>
+    async fn df(v: QueryStream<'_>) -> SsqlResult<Self::Df>
     where
         Self: Sized,
     {
-        todo!()
+        let rows = v.into_first_result().await?;
+        Ok((
+            Ta::dataframe_from_rows(&rows)?,
+            Tb::dataframe_from_rows(&rows)?,
+            Tc::dataframe_from_rows(&rows)?,
+        ))
     }
 }
 
@@ -151,43 +159,49 @@ where
     Tc: SsqlMarker,
     Td: SsqlMarker,
 {
-    fn to_struct(r: &Row) -> Self
+    fn to_struct(r: &Row) -> SsqlResult<Self>
     where
         Self: Sized + 'static,
     {
-        (
-            Ta::row_to_struct(r),
-            Tb::row_to_struct(r),
-            Tc::row_to_struct(r),
-            Td::row_to_struct(r),
-        )
+        Ok((
+            Ta::row_to_struct(r)?,
+            Tb::row_to_struct(r)?,
+            Tc::row_to_struct(r)?,
+            Td::row_to_struct(r)?,
+        ))
     }
 
     #[cfg(feature = "serde")]
     type Js = (Value, Value, Value, Value);
 
     #[cfg(feature = "serde")]
-    fn to_json(r: &Row) -> Self::Js
+    fn to_json(r: &Row) -> SsqlResult<Self::Js>
     where
         Self: Sized,
     {
-        (
-            Ta::row_to_json(r).into(),
-            Tb::row_to_json(r).into(),
-            Tc::row_to_json(r).into(),
-            Td::row_to_json(r).into(),
-        )
+        Ok((
+            Ta::row_to_json(r)?.into(),
+            Tb::row_to_json(r)?.into(),
+            Tc::row_to_json(r)?.into(),
+            Td::row_to_json(r)?.into(),
+        ))
     }
 
     #[cfg(feature = "polars")]
-    type Df = ();
+    type Df = (DataFrame, DataFrame, DataFrame, DataFrame);
 
     #[cfg(feature = "polars")]
-    async fn df(v: QueryStream<'_>) -> SsqlResult<()>
+    async fn df(v: QueryStream<'_>) -> SsqlResult<Self::Df>
     where
         Self: Sized,
     {
-        todo!()
+        let rows = v.into_first_result().await?;
+        Ok((
+            Ta::dataframe_from_rows(&rows)?,
+            Tb::dataframe_from_rows(&rows)?,
+            Tc::dataframe_from_rows(&rows)?,
+            Td::dataframe_from_rows(&rows)?,
+        ))
     }
 }
 
@@ -199,44 +213,51 @@ where
     Td: SsqlMarker,
     Te: SsqlMarker,
 {
-    fn to_struct(r: &Row) -> Self
+    fn to_struct(r: &Row) -> SsqlResult<Self>
     where
         Self: Sized + 'static,
     {
-        (
-            Ta::row_to_struct(r),
-            Tb::row_to_struct(r),
-            Tc::row_to_struct(r),
-            Td::row_to_struct(r),
-            Te::row_to_struct(r),
-        )
+        Ok((
+            Ta::row_to_struct(r)?,
+            Tb::row_to_struct(r)?,
+            Tc::row_to_struct(r)?,
+            Td::row_to_struct(r)?,
+            Te::row_to_struct(r)?,
+        ))
     }
 
     #[cfg(feature = "serde")]
     type Js = (Value, Value, Value, Value, Value);
 
     #[cfg(feature = "serde")]
-    fn to_json(r: &Row) -> Self::Js
+    fn to_json(r: &Row) -> SsqlResult<Self::Js>
     where
         Self: Sized,
     {
-        (
-            Ta::row_to_json(r).into(),
-            Tb::row_to_json(r).into(),
-            Tc::row_to_json(r).into(),
-            Td::row_to_json(r).into(),
-            Te::row_to_json(r).into(),
-        )
+        Ok((
+            Ta::row_to_json(r)?.into(),
+            Tb::row_to_json(r)?.into(),
+            Tc::row_to_json(r)?.into(),
+            Td::row_to_json(r)?.into(),
+            Te::row_to_json(r)?.into(),
+        ))
     }
 
     #[cfg(feature = "polars")]
-    type Df = ();
+    type Df = (DataFrame, DataFrame, DataFrame, DataFrame, DataFrame);
 
     #[cfg(feature = "polars")]
-    async fn df(v: QueryStream<'_>) -> SsqlResult<()>
+    async fn df(v: QueryStream<'_>) -> SsqlResult<Self::Df>
     where
         Self: Sized,
     {
-        todo!()
+        let rows = v.into_first_result().await?;
+        Ok((
+            Ta::dataframe_from_rows(&rows)?,
+            Tb::dataframe_from_rows(&rows)?,
+            Tc::dataframe_from_rows(&rows)?,
+            Td::dataframe_from_rows(&rows)?,
+            Te::dataframe_from_rows(&rows)?,
+        ))
     }
 }
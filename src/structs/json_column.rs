@@ -0,0 +1,41 @@
+//! Conversions of fields marked `#[ssql(json)]`, storing any `Serialize + DeserializeOwned` type
+//! as a JSON document in a text column such as `NVARCHAR(MAX)`.
+//! `None` and other values serialized to `null` are stored as `NULL`.
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use tiberius::ColumnData;
+
+use crate::SsqlResult;
+
+/// Serialize a field to a JSON document, failing if the value can not be serialized,
+/// e.g. a map with non-string keys.
+pub fn try_to_sql<T: Serialize>(value: &T) -> SsqlResult<ColumnData<'static>> {
+    let text = serde_json::to_string(value)
+        .map_err(|e| format!("failed to serialize json field: {}", e))?;
+    Ok(match text.as_str() {
+        "null" => ColumnData::String(None),
+        _ => ColumnData::String(Some(text.into())),
+    })
+}
+
+/// Deserialize a field from a JSON document, `NULL` being read as `null`.
+pub fn from_sql<T: DeserializeOwned>(data: &ColumnData<'static>) -> SsqlResult<T> {
+    let value = match data {
+        ColumnData::String(Some(text)) => serde_json::from_str(text),
+        ColumnData::String(None) => serde_json::from_value(Value::Null),
+        v => return Err(format!("cannot interpret {:?} as json", v).into()),
+    };
+    value.map_err(|e| format!("failed to deserialize json field: {}", e).into())
+}
+
+/// JSON document of a column as it appears in json results, invalid documents are kept as string.
+#[doc(hidden)]
+pub fn value(data: Option<&ColumnData<'static>>) -> Value {
+    match data {
+        Some(ColumnData::String(Some(text))) => {
+            serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+        }
+        _ => Value::Null,
+    }
+}
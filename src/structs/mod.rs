@@ -1,10 +1,22 @@
+pub(crate) mod bulk_insert;
+pub(crate) mod column;
+pub(crate) mod column_group;
+#[cfg(feature = "polars")]
+pub(crate) mod dataframe;
 pub(crate) mod filter;
+#[cfg(feature = "serde")]
+pub mod json_column;
 mod into_result;
 pub(crate) mod query_builder;
 pub(crate) mod query_core;
+pub(crate) mod retry;
 pub(crate) mod stream;
+pub(crate) mod table_scope;
+pub(crate) mod ssql_enum;
+pub(crate) mod ssql_keyed;
 pub(crate) mod ssql_marker;
-mod raw_query_builder;
+pub(crate) mod tracked;
+pub(crate) mod raw_query_builder;
 
 /// Represents different `JOIN` methods in sql.
 #[allow(missing_docs)]
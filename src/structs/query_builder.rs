@@ -2,14 +2,16 @@ use std::future::Future;
 use std::marker::PhantomData;
 
 use futures_lite::StreamExt;
+use tiberius::ToSql;
 use tokio::net::TcpStream;
 use tokio_util::compat::Compat;
 
 use crate::structs::into_result::IntoResult;
 use crate::structs::query_core::{Executable, QueryCore};
 use crate::structs::ssql_marker::SsqlMarker;
+use crate::structs::table_scope::TableScope;
 use crate::structs::JoinArg;
-use crate::{ColExpr, FilterExpr, RowStream, SsqlResult};
+use crate::{ColExpr, FilterExpr, InListStrategy, Retry, RetryPolicy, RowStream, SsqlError, SsqlResult};
 
 pub trait CoreVisitor<'a> {
     fn core_mut(&mut self) -> &mut QueryCore<'a>;
@@ -26,7 +28,8 @@ where
     #[doc(hidden)]
     type Ret;
 
-    /// Getting data from query builder instance, will panic if data type defined in struct is not corresponding to the tables.
+    /// Getting data from query builder instance, returns an error naming the column if data type defined in struct
+    /// is not corresponding to the tables.
     /// Returns Vector containing tuple of TABLE structs `Vec<(Ta..Te)>`, depends on how much tables joined in this query builder.
     fn all(
         &self,
@@ -36,15 +39,18 @@ where
             let mut stream = self.core_ref().execute(conn).await?.into_row_stream();
             let mut ret = vec![];
             while let Some(row) = stream.try_next().await? {
-                ret.push(Self::Ret::to_struct(&row));
+                ret.push(Self::Ret::to_struct(&row)?);
             }
             Ok(ret)
         }
     }
 
     /// Similar to [`all`], but returns a stream producing tuple of structs instead of a whole vector.
+    /// Each item is a [`SsqlResult`], see [`RowStream`] for helpers on such stream.
     ///
     /// [`all`]: trait.QueryAble.html#method.all
+    /// [`SsqlResult`]: type.SsqlResult.html
+    /// [`RowStream`]: struct.RowStream.html
     fn stream<'b>(
         &self,
         conn: &'b mut tiberius::Client<Compat<TcpStream>>,
@@ -66,11 +72,43 @@ where
             let row = self.core_ref().execute(conn).await?.into_row().await?;
             match row {
                 None => Ok(None),
-                Some(row) => Ok(Some(Self::Ret::to_struct(&row))),
+                Some(row) => Ok(Some(Self::Ret::to_struct(&row)?)),
             }
         }
     }
 
+    /// Similar to [`one`], but returns [`SsqlError::NoRows`] instead of `None` if nothing matched.
+    ///
+    /// [`one`]: trait.QueryAble.html#method.one
+    /// [`SsqlError::NoRows`]: enum.SsqlError.html#variant.NoRows
+    fn first(
+        &self,
+        conn: &mut tiberius::Client<Compat<TcpStream>>,
+    ) -> impl Future<Output = SsqlResult<Self::Ret>> + Send {
+        async move {
+            self.one(conn)
+                .await?
+                .ok_or_else(|| SsqlError::NoRows(self.core_ref().main_table.to_string()))
+        }
+    }
+
+    /// Wrap the query builder to retry [`all`], [`one`], [`first`] and [`json`] with a [`RetryPolicy`].
+    ///
+    /// [`all`]: trait.QueryAble.html#method.all
+    /// [`one`]: trait.QueryAble.html#method.one
+    /// [`first`]: trait.QueryAble.html#method.first
+    /// [`json`]: trait.QueryAble.html#method.json
+    /// [`RetryPolicy`]: struct.RetryPolicy.html
+    fn retry<'q>(&'q self, policy: &'q RetryPolicy) -> Retry<'q, Self>
+    where
+        Self: Sized,
+    {
+        Retry {
+            query: self,
+            policy,
+        }
+    }
+
     /// Similar to [`all`], but returns Vector containing tuple of [`Value`] instead of struct itself.
     ///
     /// [`all`]: trait.QueryAble.html#method.all
@@ -85,13 +123,18 @@ where
             let mut stream = self.core_ref().execute(conn).await?.into_row_stream();
             let mut ret = vec![];
             while let Some(row) = stream.try_next().await? {
-                ret.push(Self::Ret::to_json(&row))
+                ret.push(Self::Ret::to_json(&row)?)
             }
             Ok(ret)
         }
     }
 
     /// Similar to [`all`], but returns [`Polars DataFrame`] representing the query result.
+    /// Joined queries return a tuple with one DataFrame per table in the order of the query,
+    /// all of the same height, a row of the outer side of a join without a match being all nulls.
+    ///
+    /// Chrono fields become `Datetime` (in microseconds, `UTC` for `DateTime<Utc>`), `Date` and `Time` columns,
+    /// `Numeric` a `Decimal`, `Vec<u8>` a `Binary` and `Uuid` a `String` column.
     ///
     /// [`all`]: trait.QueryAble.html#method.all
     /// [`Polars DataFrame`]: polars::prelude::DataFrame
@@ -104,9 +147,64 @@ where
         async move { Self::Ret::df(self.core_ref().execute(conn).await?).await }
     }
 
+    /// Delete all rows of the main table that matched the filters of current builder,
+    /// joined tables can be referred in filters. Returns the number of affected rows.
+    /// Be careful, calling it without any filter deletes the whole table.
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    /// # #[derive(ORM)]
+    /// # #[ssql(table = person)]
+    /// # struct Person{
+    /// #    id: i32,
+    /// #    email: Option<String>,
+    /// # }
+    /// # async fn _test(conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<()> {
+    /// let affected = Person::query()
+    ///     .filter(Person::col("email")?.is_null())?
+    ///     .delete(conn)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// SQL: `DELETE person FROM person WHERE person.email IS NULL`
+    fn delete(
+        &self,
+        conn: &mut tiberius::Client<Compat<TcpStream>>,
+    ) -> impl Future<Output = SsqlResult<u64>> + Send {
+        async move { self.core_ref().delete(conn).await }
+    }
+
+    /// Update columns of the main table for all rows that matched the filters of current builder,
+    /// joined tables can be referred in filters. Returns the number of affected rows.
+    /// Only columns of the main table can be updated.
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    /// # #[derive(ORM)]
+    /// # #[ssql(table = person)]
+    /// # struct Person{
+    /// #    id: i32,
+    /// #    email: Option<String>,
+    /// # }
+    /// # async fn _test(conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<()> {
+    /// let affected = Person::query()
+    ///     .filter(Person::col("id")?.gt(&100))?
+    ///     .update_set(&[(Person::col("email")?, &"unknown")], conn)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// SQL: `UPDATE person SET email = 'unknown' FROM person WHERE person.id > 100`
+    fn update_set(
+        &self,
+        values: &[(ColExpr, &dyn ToSql)],
+        conn: &mut tiberius::Client<Compat<TcpStream>>,
+    ) -> impl Future<Output = SsqlResult<u64>> + Send {
+        async move { self.core_ref().update(values, conn).await }
+    }
+
     /// Perform left join on another table.
-    /// Will panic if the relationship not presented in field attribute `#[ssql(foreign_key=...)]`
-    /// or if the provided table is already joined.
+    /// Will panic if the provided table is already joined.
+    /// Executing the query returns an error if the relationship is not presented in field attribute `#[ssql(foreign_key=...)]`.
     /// ```no_run
     /// # use ssql::prelude::*;
     /// #[derive(ORM)]
@@ -187,21 +285,86 @@ where
         Ok(self)
     }
 
+    /// Set how [`is_in`] filters exceeding the parameter limit of SQL Server are sent,
+    /// applies to filters chained after this call. See [`InListStrategy`].
+    ///
+    /// [`is_in`]: struct.ColExpr.html#method.is_in
+    /// [`InListStrategy`]: enum.InListStrategy.html
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    /// # #[derive(ORM)]
+    /// # #[ssql(table = person)]
+    /// # struct Person{
+    /// #    id: i32,
+    /// #    email: Option<String>,
+    /// # }
+    /// # fn _test() -> SsqlResult<()> {
+    /// let ids: Vec<i32> = (0..5000).collect();
+    /// let query = Person::query()
+    ///     .in_list_strategy(ssql::InListStrategy::StringSplit)
+    ///     .filter(Person::col("id")?.is_in(&ids))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// SQL: `... WHERE person.id IN (SELECT value FROM STRING_SPLIT(@p1, ','))`
+    fn in_list_strategy(mut self, strategy: InListStrategy) -> Self
+    where
+        Self: Sized,
+    {
+        self.core_mut().in_list_strategy = strategy;
+        self
+    }
+
+    /// Resolve every table of this builder in the given schema instead of the one it is derived with.
+    /// Rows are still mapped to the same structs, filters and joins are unaffected.
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    /// # #[derive(ORM)]
+    /// # #[ssql(table = person, schema = dbo)]
+    /// # struct Person{
+    /// #    id: i32,
+    /// #    email: Option<String>,
+    /// # }
+    /// # async fn _test(mut conn: Client<Compat<TcpStream>>) -> SsqlResult<()> {
+    /// let persons = Person::query().in_schema("tenant42").all(&mut conn).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// SQL: `SELECT ... FROM [tenant42].[person] AS [dbo.person]`
+    fn in_schema(self, schema: &str) -> Self
+    where
+        Self: Sized,
+    {
+        self.in_scope(TableScope::schema(schema))
+    }
+
+    /// Same as [`in_schema`], with a [`TableScope`] that may also name the database.
+    ///
+    /// [`in_schema`]: trait.QueryAble.html#method.in_schema
+    /// [`TableScope`]: struct.TableScope.html
+    fn in_scope(mut self, scope: TableScope) -> Self
+    where
+        Self: Sized,
+    {
+        self.core_mut().scope = scope;
+        self
+    }
+
     /// Ordering the output by a specified column in ascending order.
-    fn order_by_asc(mut self, col_expr: ColExpr) -> SsqlResult<Self>
+    fn order_by_asc(mut self, col_expr: impl Into<ColExpr>) -> SsqlResult<Self>
     where
         Self: Sized,
     {
-        self.core_mut().order_by(col_expr, true)?;
+        self.core_mut().order_by(col_expr.into(), true)?;
         Ok(self)
     }
 
     /// Ordering the output by a specified column in descending order.
-    fn order_by_desc(mut self, col_expr: ColExpr) -> SsqlResult<Self>
+    fn order_by_desc(mut self, col_expr: impl Into<ColExpr>) -> SsqlResult<Self>
     where
         Self: Sized,
     {
-        self.core_mut().order_by(col_expr, false)?;
+        self.core_mut().order_by(col_expr.into(), false)?;
         Ok(self)
     }
 }
@@ -222,7 +385,7 @@ where
     /// Create a new query builder, shouldn't call it manually, this is handled by [`query`] method.
     ///
     /// ['query`]: trait.SsqlMarker.html#tymethod.query
-    pub fn new(fields: (&'static str, Vec<&'static str>), func: fn(&str) -> &'static str) -> Self {
+    pub fn new(fields: (&'static str, Vec<&'static str>), func: fn(&str) -> Option<&'static str>) -> Self {
         let core = QueryCore::new(fields, func);
         Self {
             core,
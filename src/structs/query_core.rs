@@ -6,11 +6,32 @@ use tiberius::{QueryStream, ToSql};
 use tokio::net::TcpStream;
 use tokio_util::compat::Compat;
 
-use crate::error::custom_error::SsqlResult;
-use crate::structs::filter::{ColExpr, FilterExpr};
+use crate::error::custom_error::{SsqlError, SsqlResult};
+use crate::structs::filter::{ColExpr, FilterExpr, InListStrategy};
 use crate::structs::ssql_marker::SsqlMarker;
 use crate::structs::stream::RowStream;
 use crate::structs::JoinArg;
+use crate::structs::table_scope::TableScope;
+use crate::utils::quote_ident;
+
+/// Maximum number of parameters of a query, SQL Server accepts 2100 parameters in one request
+/// of which `sp_executesql` takes 2 for the statement and the parameter declarations.
+pub(crate) const PARAM_LIMIT: usize = 2098;
+
+/// Query parameter, either borrowed from the caller or generated while building the query.
+pub(crate) enum Param<'a> {
+    Ref(&'a dyn ToSql),
+    Owned(String),
+}
+
+impl<'a> Param<'a> {
+    pub(crate) fn as_dyn(&self) -> &dyn ToSql {
+        match self {
+            Param::Ref(v) => *v,
+            Param::Owned(v) => v,
+        }
+    }
+}
 
 pub struct RawQuery;
 
@@ -38,7 +59,14 @@ where
                 .map(|(table, fields)| {
                     fields
                         .iter()
-                        .map(|field| format!(r#"{}.{} as "{}.{}""#, table, field, table, field))
+                        .map(|field| {
+                            format!(
+                                "{}.{} AS {}",
+                                quote_ident(table),
+                                quote_ident(field),
+                                quote_ident(&format!("{}.{}", table, field))
+                            )
+                        })
                         .reduce(|cur, nxt| format!("{},{}", cur, nxt))
                         .unwrap()
                 })
@@ -53,15 +81,16 @@ where
 
             // let mut stream = conn.simple_query(r#"SELECT ship_to_id as "CUSTOMER_LIST.ship_to_id", ship_to as "CUSTOMER_LIST.ship_to",
             // volume as "CUSTOMER_LIST.volume", container as "CUSTOMER_LIST.container" FROM CUSTOMER_LIST"#).await.unwrap();
+            let sql = format!(
+                "SELECT {} FROM {} {} {where_clause} {order_clause}",
+                select_fields,
+                self.aliased_table(self.main_table),
+                self.get_join_clause()?
+            );
             let stream = conn
-                .query(
-                    format!(
-                        "SELECT {} FROM {} {} {where_clause} {order_clause}",
-                        select_fields, self.main_table, self.join
-                    ),
-                    self.query_params.as_slice(),
-                )
-                .await?;
+                .query(sql.as_str(), self.params()?.as_slice())
+                .await
+                .map_err(|e| SsqlError::with_sql(e, &sql))?;
             Ok(stream)
         }
     }
@@ -76,9 +105,11 @@ where
         conn: &'b mut tiberius::Client<Compat<TcpStream>>,
     ) -> impl Future<Output = SsqlResult<QueryStream<'b>>> + Send {
         async move {
+            let sql = self.raw_sql.as_ref().unwrap();
             let stream = conn
-                .query(self.raw_sql.as_ref().unwrap(), self.query_params.as_slice())
-                .await?;
+                .query(sql, self.params()?.as_slice())
+                .await
+                .map_err(|e| SsqlError::with_sql(e, sql))?;
             Ok(stream)
         }
     }
@@ -88,16 +119,18 @@ where
 ///
 /// [`TableStruct::query()`]: trait.SsqlMarker.html#tymethod.query
 pub struct QueryCore<'a, Stage = NormalQuery> {
-    main_table: &'static str,
+    pub(crate) main_table: &'static str,
     pub(crate) fields: HashMap<&'static str, Vec<&'static str>>,
     pub(crate) filters: Vec<String>,
-    pub(crate) join: String,
+    joins: Vec<(JoinArg, &'static str)>,
     tables: HashSet<&'static str>,
     order: String,
     pub(crate) raw_sql: Option<String>,
-    relation_func: fn(&str) -> &'static str,
-    pub(crate) query_params: Vec<&'a dyn ToSql>,
+    relation_func: fn(&str) -> Option<&'static str>,
+    pub(crate) query_params: Vec<Param<'a>>,
     query_idx_counter: i32,
+    pub(crate) in_list_strategy: InListStrategy,
+    pub(crate) scope: TableScope,
 
     // _marker: Option<PhantomData<T>>,
     _mark2: PhantomData<Stage>,
@@ -114,7 +147,7 @@ where
         func: F,
     ) -> SsqlResult<RowStream<'a, Ret>>
     where
-        F: 'static + for<'b> Fn(&'b tiberius::Row) -> Ret + Send,
+        F: 'static + for<'b> Fn(&'b tiberius::Row) -> SsqlResult<Ret> + Send,
     {
         let query_stream = self.execute(conn).await?;
         Ok(RowStream::new(query_stream, func))
@@ -127,7 +160,7 @@ where
 {
     pub(crate) fn new<'b: 'a>(
         fields: (&'static str, Vec<&'static str>),
-        func: fn(&str) -> &'static str,
+        func: fn(&str) -> Option<&'static str>,
     ) -> QueryCore<'b>
 // where
     //     C: SsqlMarker,
@@ -137,11 +170,13 @@ where
             tables: HashSet::from([fields.0]),
             fields: HashMap::from([fields]),
             filters: vec![],
-            join: String::new(),
+            joins: vec![],
             relation_func: func,
             raw_sql: None,
             query_params: vec![], // use for filter
             query_idx_counter: 0, // use for filter
+            in_list_strategy: Default::default(),
+            scope: Default::default(),
             _mark2: PhantomData,
 
             order: "".to_string(),
@@ -152,11 +187,14 @@ where
         // self.query_params.push(filter_expr.conditions);
         match self.tables.contains(filter_expr.col.table) {
             true => {
-                self.filters
-                    .push(filter_expr.to_sql(&mut self.query_idx_counter, &mut self.query_params));
+                self.filters.push(filter_expr.to_sql(
+                    &mut self.query_idx_counter,
+                    &mut self.query_params,
+                    self.in_list_strategy,
+                )?);
                 Ok(())
             }
-            false => Err("the filter applies to a table not in this builder".into()),
+            false => Err(SsqlError::TableNotInBuilder(filter_expr.col.table.to_string())),
         }
     }
 
@@ -173,7 +211,7 @@ where
                 }
                 Ok(())
             }
-            false => Err("Try to make order on a table not in this builder".into()),
+            false => Err(SsqlError::TableNotInBuilder(column.table.to_string())),
         }
     }
 
@@ -181,17 +219,9 @@ where
     where
         B: SsqlMarker,
     {
-        let join_type = match join_args {
-            JoinArg::Left => "LEFT",
-            JoinArg::Right => "RIGHT",
-            JoinArg::Outer => "OUTER",
-            JoinArg::Inner => "INNER",
-        };
         let name = B::table_name();
         let fields = B::fields();
-        let relation = self.find_relation(&name);
-        self.join
-            .push_str(&format!(" {} JOIN {} ", join_type, relation));
+        self.joins.push((join_args, name));
         match self.fields.insert(&name, fields) {
             Some(_v) => panic!("table already joined."),
             None => {
@@ -201,8 +231,103 @@ where
         self
     }
 
-    fn find_relation(&self, table: &str) -> &'static str {
-        (self.relation_func)(table)
+    pub(crate) async fn delete(
+        &self,
+        conn: &mut tiberius::Client<Compat<TcpStream>>,
+    ) -> SsqlResult<u64> {
+        let where_clause = self.get_where_clause();
+        let sql = format!(
+            "DELETE {} FROM {} {} {where_clause}",
+            quote_ident(self.main_table),
+            self.aliased_table(self.main_table),
+            self.get_join_clause()?,
+        );
+        let result = conn
+            .execute(sql.as_str(), self.params()?.as_slice())
+            .await
+            .map_err(|e| SsqlError::with_sql(e, &sql))?;
+        Ok(result.total())
+    }
+
+    pub(crate) async fn update(
+        &self,
+        values: &[(ColExpr, &dyn ToSql)],
+        conn: &mut tiberius::Client<Compat<TcpStream>>,
+    ) -> SsqlResult<u64> {
+        if values.is_empty() {
+            return Err("no column provided for update".into());
+        }
+        let mut idx = self.query_idx_counter;
+        let mut params: Vec<&dyn ToSql> = self.query_params.iter().map(|p| p.as_dyn()).collect();
+        let mut set_clause = vec![];
+        for (col, value) in values {
+            if col.table != self.main_table {
+                return Err(format!(
+                    "column {} does not belong to main table {}",
+                    col.full_column_name(),
+                    self.main_table
+                )
+                .into());
+            }
+            if col.json_path.is_some() {
+                return Err(format!(
+                    "can not update {}, JSON values are updated with the whole column",
+                    col.full_column_name()
+                )
+                .into());
+            }
+            idx += 1;
+            set_clause.push(format!("{} = @p{}", quote_ident(col.field), idx));
+            params.push(*value);
+        }
+        let params = check_params(params)?;
+
+        let where_clause = self.get_where_clause();
+        let sql = format!(
+            "UPDATE {} SET {} FROM {} {} {where_clause}",
+            quote_ident(self.main_table),
+            set_clause.join(", "),
+            self.aliased_table(self.main_table),
+            self.get_join_clause()?,
+        );
+        let result = conn
+            .execute(sql.as_str(), params.as_slice())
+            .await
+            .map_err(|e| SsqlError::with_sql(e, &sql))?;
+        Ok(result.total())
+    }
+
+    fn find_relation(&self, table: &str) -> SsqlResult<&'static str> {
+        (self.relation_func)(table).ok_or_else(|| SsqlError::RelationshipNotFound {
+            from: self.main_table.to_string(),
+            to: table.to_string(),
+        })
+    }
+
+    /// Table resolved in the scope of this query, aliased with its derived name
+    /// so that columns can be referred to regardless of the scope.
+    fn aliased_table(&self, table: &str) -> String {
+        format!("{} AS {}", self.scope.resolve(table), quote_ident(table))
+    }
+
+    fn get_join_clause(&self) -> SsqlResult<String> {
+        self.joins
+            .iter()
+            .map(|(join_args, table)| {
+                let join_type = match join_args {
+                    JoinArg::Left => "LEFT",
+                    JoinArg::Right => "RIGHT",
+                    JoinArg::Outer => "OUTER",
+                    JoinArg::Inner => "INNER",
+                };
+                Ok(format!(
+                    " {} JOIN {} ON {}",
+                    join_type,
+                    self.aliased_table(table),
+                    self.find_relation(table)?
+                ))
+            })
+            .collect()
     }
 
     fn get_where_clause(&self) -> String {
@@ -218,20 +343,53 @@ where
     }
 }
 
+impl<'a, Stage> QueryCore<'a, Stage> {
+    /// Parameters to be sent along with the query, checked against the parameter limit.
+    pub(crate) fn params(&self) -> SsqlResult<Vec<&dyn ToSql>> {
+        check_params(self.query_params.iter().map(|p| p.as_dyn()).collect())
+    }
+}
+
+/// Check all parameters of a statement against the parameter limit,
+/// as exceeding it fails on the server.
+fn check_params(params: Vec<&dyn ToSql>) -> SsqlResult<Vec<&dyn ToSql>> {
+    match params.len() <= PARAM_LIMIT {
+        true => Ok(params),
+        false => Err(format!(
+            "query has {} parameters, exceeding the limit of {} allowed by SQL Server",
+            params.len(),
+            PARAM_LIMIT
+        )
+        .into()),
+    }
+}
+
 impl Default for QueryCore<'_, RawQuery> {
     fn default() -> Self {
         QueryCore {
             main_table: "",
             fields: Default::default(),
             filters: vec![],
-            join: "".to_string(),
+            joins: vec![],
             tables: Default::default(),
             order: "".to_string(),
             raw_sql: None,
-            relation_func: |_| "",
+            relation_func: |_| None,
             query_params: vec![],
             query_idx_counter: 0,
+            in_list_strategy: Default::default(),
+            scope: Default::default(),
             _mark2: Default::default(),
         }
     }
 }
+
+impl<'a> QueryCore<'a, RawQuery> {
+    pub(crate) fn raw(sql: &str, params: &[&'a dyn ToSql]) -> Self {
+        QueryCore {
+            raw_sql: Some(sql.to_string()),
+            query_params: params.iter().map(|p| Param::Ref(*p)).collect(),
+            ..Default::default()
+        }
+    }
+}
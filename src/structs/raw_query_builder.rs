@@ -1,13 +1,19 @@
 use std::marker::PhantomData;
 
 use futures_lite::StreamExt;
+#[cfg(feature = "polars")]
+use polars::frame::DataFrame;
 #[cfg(feature = "serde")]
 use serde_json::Value;
 use tiberius::Client;
+#[cfg(feature = "polars")]
+use tiberius::{ColumnData, QueryItem, ToSql};
 use tokio::net::TcpStream;
 use tokio_util::compat::Compat;
 
 use crate::{RowStream, SsqlMarker, SsqlResult};
+#[cfg(feature = "polars")]
+use crate::structs::dataframe::column_series;
 use crate::structs::into_result::IntoResult;
 use crate::structs::query_core::{Executable, QueryCore, RawQuery};
 
@@ -27,7 +33,7 @@ where
         let mut stream = self.core.execute(conn).await?.into_row_stream();
         let mut ret = vec![];
         while let Some(row) = stream.try_next().await? {
-            ret.push(T::to_struct(&row));
+            ret.push(T::to_struct(&row)?);
         }
         Ok(ret)
     }
@@ -47,7 +53,7 @@ where
         let row = self.core.execute(conn).await?.into_row().await?;
         match row {
             None => Ok(None),
-            Some(row) => Ok(Some(T::to_struct(&row))),
+            Some(row) => Ok(Some(T::to_struct(&row)?)),
         }
     }
 
@@ -59,8 +65,64 @@ where
         let mut stream = self.core.execute(conn).await?.into_row_stream();
         let mut ret = vec![];
         while let Some(row) = stream.try_next().await? {
-            ret.push(T::to_json(&row))
+            ret.push(T::to_json(&row)?)
         }
         Ok(ret)
     }
 }
+
+/// Raw query without a struct describing its result, built with [`UntypedRawQuery::new`],
+/// its result being read into a DataFrame whose dtypes follow the SQL types of the result set.
+/// Only the first result set of the query is read.
+///
+/// [`UntypedRawQuery::new`]: struct.UntypedRawQuery.html#method.new
+/// ```no_run
+/// # use ssql::prelude::*;
+/// async fn _test(conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<()> {
+///     let df = UntypedRawQuery::new("SELECT id, email, dt FROM Person WHERE id > @p1", &[&1])
+///         .df(conn)
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "polars")]
+pub struct UntypedRawQuery<'a> {
+    core: QueryCore<'a, RawQuery>,
+}
+
+#[cfg(feature = "polars")]
+impl<'a> UntypedRawQuery<'a> {
+    /// Raw query with its parameters, referred as `@p1`, `@p2`... in `sql`.
+    pub fn new(sql: &str, params: &[&'a dyn ToSql]) -> Self {
+        Self {
+            core: QueryCore::raw(sql, params),
+        }
+    }
+
+    /// Read the result into a DataFrame with one column per column of the result set.
+    pub async fn df(&self, conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<DataFrame> {
+        let mut stream = self.core.execute(conn).await?;
+        let mut columns = vec![];
+        let mut values: Vec<Vec<ColumnData<'static>>> = vec![];
+        while let Some(item) = stream.try_next().await? {
+            match item {
+                QueryItem::Metadata(meta) if meta.result_index() == 0 => {
+                    columns = meta.columns().to_vec();
+                    values = columns.iter().map(|_| vec![]).collect();
+                }
+                QueryItem::Row(row) if row.result_index() == 0 => {
+                    for (column, data) in values.iter_mut().zip(row) {
+                        column.push(data);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let series = columns
+            .iter()
+            .zip(values)
+            .map(|(column, values)| column_series(column, values))
+            .collect::<SsqlResult<Vec<_>>>()?;
+        Ok(DataFrame::new(series)?)
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::time::Duration;
+
+use tiberius::Client;
+use tokio::net::TcpStream;
+use tokio_util::compat::Compat;
+
+use crate::structs::into_result::IntoResult;
+use crate::structs::query_builder::QueryAble;
+use crate::{SsqlError, SsqlResult};
+
+/// Future returned by a unit of work retried with [`RetryPolicy::run`].
+///
+/// [`RetryPolicy::run`]: struct.RetryPolicy.html#method.run
+pub type RetryFuture<'c, T> = Pin<Box<dyn Future<Output = SsqlResult<T>> + Send + 'c>>;
+
+/// Policy re-executing statements that failed with a transient error, such as a deadlock victim (1205),
+/// a timeout or a transient error of Azure SQL Database, see [`SsqlError::is_retryable`].
+/// Constructed with [`RetryPolicy::new`] and chained setters, then used by [`run`] or [`QueryAble::retry`].
+///
+/// Attempts are delayed with exponential backoff, from `base` doubling up to `max`,
+/// with a random jitter of up to half the delay so that concurrent jobs do not retry in lockstep.
+///
+/// A statement is not retried inside a transaction opened before the retry,
+/// as SQL Server rolls back the whole transaction along with a deadlock victim.
+/// Such a transaction should be opened and committed inside the unit passed to [`run`] instead,
+/// a transaction left open by a failed attempt being rolled back before the next one.
+///
+/// [`SsqlError::is_retryable`]: enum.SsqlError.html#method.is_retryable
+/// [`RetryPolicy::new`]: struct.RetryPolicy.html#method.new
+/// [`run`]: struct.RetryPolicy.html#method.run
+/// [`QueryAble::retry`]: trait.QueryAble.html#method.retry
+/// ```no_run
+/// # use ssql::prelude::*;
+/// # use std::time::Duration;
+/// # #[derive(ORM, Clone)]
+/// # #[ssql(table = person)]
+/// # struct Person {
+/// #     #[ssql(primary_key)]
+/// #     id: i32,
+/// # }
+/// async fn _test(conn: &mut Client<Compat<TcpStream>>, person: &Person) -> SsqlResult<()> {
+///     let policy = RetryPolicy::new()
+///         .max_attempts(5)
+///         .backoff(Duration::from_millis(200), Duration::from_secs(10));
+///
+///     let persons = Person::query().retry(&policy).all(conn).await?;
+///
+///     policy
+///         .run(conn, |conn, _| Box::pin(person.clone().insert(conn)))
+///         .await?;
+///
+///     // the whole transaction is retried
+///     policy
+///         .run(conn, |conn, _| {
+///             Box::pin(async move {
+///                 conn.simple_query("BEGIN TRANSACTION").await?.into_results().await?;
+///                 person.update(conn).await?;
+///                 Person::query().filter(Person::ID.eq(&1))?.delete(conn).await?;
+///                 conn.simple_query("COMMIT").await?.into_results().await?;
+///                 Ok(())
+///             })
+///         })
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    retry_on: fn(&SsqlError) -> bool,
+}
+
+/// Attempt of a unit of work retried with [`RetryPolicy::run`],
+/// also binding the lifetimes of the connection and the data borrowed by the unit.
+///
+/// [`RetryPolicy::run`]: struct.RetryPolicy.html#method.run
+pub struct Attempt<'c, 'q> {
+    number: u32,
+    _marker: PhantomData<&'c &'q ()>,
+}
+
+impl Attempt<'_, '_> {
+    /// Number of the attempt, starting from 1.
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            jitter: true,
+            retry_on: SsqlError::is_retryable,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Default policy, 3 attempts delayed from 100ms up to 5s with jitter, retrying errors that are [`retryable`].
+    ///
+    /// [`retryable`]: enum.SsqlError.html#method.is_retryable
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of attempts including the first one, 1 disabling retries.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Delay before the first retry, doubled for each following retry up to `max`.
+    pub fn backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.base_delay = base;
+        self.max_delay = max.max(base);
+        self
+    }
+
+    /// Whether to shorten delays by a random amount of up to half the delay, enabled by default.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Errors to retry, instead of those that are [`retryable`].
+    ///
+    /// [`retryable`]: enum.SsqlError.html#method.is_retryable
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    /// let policy = RetryPolicy::new().retry_on(|e| matches!(e, ssql::SsqlError::Deadlock(_)));
+    /// ```
+    pub fn retry_on(mut self, retry_on: fn(&SsqlError) -> bool) -> Self {
+        self.retry_on = retry_on;
+        self
+    }
+
+    /// Run a unit of work, running it again while it fails with an error to retry.
+    /// The unit returns a boxed future borrowing the connection, see the example of [`RetryPolicy`].
+    ///
+    /// [`RetryPolicy`]: struct.RetryPolicy.html
+    pub async fn run<'q, T, F>(&self, conn: &mut Client<Compat<TcpStream>>, mut unit: F) -> SsqlResult<T>
+    where
+        F: for<'c> FnMut(&'c mut Client<Compat<TcpStream>>, Attempt<'c, 'q>) -> RetryFuture<'c, T>,
+    {
+        let max_attempts = match self.max_attempts > 1 && in_transaction(conn).await? {
+            true => 1,
+            false => self.max_attempts,
+        };
+        let mut number = 1;
+        loop {
+            let attempt = Attempt {
+                number,
+                _marker: PhantomData,
+            };
+            let err = match unit(conn, attempt).await {
+                Ok(ret) => return Ok(ret),
+                Err(e) => e,
+            };
+            if number >= max_attempts || !(self.retry_on)(&err) {
+                return Err(err);
+            }
+            // the error of the unit is more useful than the one of a broken connection
+            if rollback(conn).await.is_err() {
+                return Err(err);
+            }
+            tokio::time::sleep(self.delay(number)).await;
+            number += 1;
+        }
+    }
+
+    // delay before the attempt following `attempt`
+    fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+        match self.jitter {
+            true => {
+                let random = RandomState::new().build_hasher().finish();
+                delay - delay.mul_f64((random % 1000) as f64 / 2000.0)
+            }
+            false => delay,
+        }
+    }
+}
+
+async fn in_transaction(conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<bool> {
+    let row = conn
+        .simple_query("SELECT @@TRANCOUNT")
+        .await?
+        .into_row()
+        .await?;
+    Ok(row.and_then(|r| r.get::<i32, _>(0)).unwrap_or(0) > 0)
+}
+
+async fn rollback(conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<()> {
+    conn.simple_query("IF @@TRANCOUNT > 0 ROLLBACK")
+        .await?
+        .into_results()
+        .await?;
+    Ok(())
+}
+
+/// Query builder retried with a [`RetryPolicy`], returned by [`QueryAble::retry`].
+/// Streams are not retried as rows already yielded can not be taken back,
+/// process the stream inside [`RetryPolicy::run`] to retry the whole processing.
+///
+/// [`RetryPolicy`]: struct.RetryPolicy.html
+/// [`QueryAble::retry`]: trait.QueryAble.html#method.retry
+/// [`RetryPolicy::run`]: struct.RetryPolicy.html#method.run
+pub struct Retry<'q, Q> {
+    pub(crate) query: &'q Q,
+    pub(crate) policy: &'q RetryPolicy,
+}
+
+impl<'q, 'a, Q> Retry<'q, Q>
+where
+    Q: QueryAble<'a>,
+    Q::Ret: IntoResult + Send + Sync + 'static,
+{
+    /// Same as [`QueryAble::all`](trait.QueryAble.html#method.all), retried.
+    pub async fn all(&self, conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<Vec<Q::Ret>> {
+        let query = self.query;
+        self.policy
+            .run(conn, |conn, _| Box::pin(query.all(conn)))
+            .await
+    }
+
+    /// Same as [`QueryAble::one`](trait.QueryAble.html#method.one), retried.
+    pub async fn one(&self, conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<Option<Q::Ret>> {
+        let query = self.query;
+        self.policy
+            .run(conn, |conn, _| Box::pin(query.one(conn)))
+            .await
+    }
+
+    /// Same as [`QueryAble::first`](trait.QueryAble.html#method.first), retried.
+    pub async fn first(&self, conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<Q::Ret> {
+        let query = self.query;
+        self.policy
+            .run(conn, |conn, _| Box::pin(query.first(conn)))
+            .await
+    }
+
+    /// Same as [`QueryAble::json`](trait.QueryAble.html#method.json), retried.
+    #[cfg(feature = "serde")]
+    pub async fn json(
+        &self,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> SsqlResult<Vec<<Q::Ret as IntoResult>::Js>> {
+        let query = self.query;
+        self.policy
+            .run(conn, |conn, _| Box::pin(query.json(conn)))
+            .await
+    }
+}
@@ -0,0 +1,145 @@
+use tiberius::{ColumnData, IntoSql, ToSql};
+
+/// A trait automatically derived via `#[derive(SsqlEnum)]` macro, mapping a fieldless enum to a
+/// string or integer column. Deriving it also implements `ToSql`, `IntoSql` and `FromSql`,
+/// so the enum can be used as field type and in filters like `col.eq(&Status::Active)`.
+///
+/// Variants are stored as their name, or the name given by `#[ssql(rename = "...")]`.
+/// With `#[ssql(repr = "i32")]` on the enum, variants are stored as their discriminant instead,
+/// `repr` being one of `u8`, `i16`, `i32` or `i64`.
+/// Fields of the enum may be nullable, and as any other field type, the enum needs to implement
+/// `Serialize` if feature `serde` is enabled. In DataFrames it is held as a column of the stored codes.
+/// ```
+/// # use ssql::prelude::*;
+/// # use serde::Serialize;
+/// #[derive(SsqlEnum, Serialize, Debug, PartialEq)]
+/// enum Status {
+///     #[ssql(rename = "ACTIVE")]
+///     Active,
+///     #[ssql(rename = "CLOSED")]
+///     Closed,
+/// }
+///
+/// #[derive(SsqlEnum, Serialize, Debug, PartialEq)]
+/// #[ssql(repr = "u8")]
+/// enum Level {
+///     Low = 1,
+///     High = 10,
+/// }
+///
+/// #[derive(ORM)]
+/// #[ssql(table = orders)]
+/// struct Order {
+///     #[ssql(primary_key)]
+///     id: i32,
+///     status: Status,
+///     level: Option<Level>,
+/// }
+/// # fn _test() -> SsqlResult<()> {
+/// let query = Order::query().filter(Order::col("status")?.eq(&Status::Active))?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait SsqlEnum: Sized + Send + Sync {
+    #[doc(hidden)]
+    fn to_column(&self) -> ColumnData<'static>;
+    #[doc(hidden)]
+    fn null_column() -> ColumnData<'static>;
+    #[doc(hidden)]
+    fn from_column(data: &ColumnData<'static>) -> tiberius::Result<Option<Self>>;
+}
+
+/// Sql value of a nullable field, used by the code generated with `#[derive(ORM)]`.
+/// `Option<T>` can not implement `ToSql` for enums deriving [`SsqlEnum`], as both are foreign
+/// to the crate deriving it, so they are implemented here.
+/// `M` tells the implementations apart and is inferred from the field type.
+///
+/// [`SsqlEnum`]: trait.SsqlEnum.html
+#[doc(hidden)]
+pub trait FieldValue<M> {
+    fn field_value(&self) -> ColumnData<'_>;
+    fn into_field_value(self) -> ColumnData<'static>;
+}
+
+#[doc(hidden)]
+pub struct ViaToSql;
+
+#[doc(hidden)]
+pub struct ViaEnum;
+
+impl<T: ToSql + IntoSql<'static>> FieldValue<ViaToSql> for T {
+    fn field_value(&self) -> ColumnData<'_> {
+        self.to_sql()
+    }
+
+    fn into_field_value(self) -> ColumnData<'static> {
+        self.into_sql()
+    }
+}
+
+impl<T: SsqlEnum> FieldValue<ViaEnum> for Option<T> {
+    fn field_value(&self) -> ColumnData<'_> {
+        match self {
+            Some(v) => v.to_column(),
+            None => T::null_column(),
+        }
+    }
+
+    fn into_field_value(self) -> ColumnData<'static> {
+        match self {
+            Some(v) => v.to_column(),
+            None => T::null_column(),
+        }
+    }
+}
+
+/// Conversions of enum fields for `#[ssql(with = "ssql::enum_column")]`,
+/// for both `T` and `Option<T>` where `T` derives [`SsqlEnum`].
+/// Such fields output the stored code in json results instead of the serialized enum.
+///
+/// [`SsqlEnum`]: trait.SsqlEnum.html
+pub mod enum_column {
+    use tiberius::ColumnData;
+
+    use crate::{SsqlEnum, SsqlResult};
+
+    #[doc(hidden)]
+    pub trait EnumColumn: Sized {
+        fn to_column(&self) -> ColumnData<'static>;
+        fn from_column(data: &ColumnData<'static>) -> SsqlResult<Self>;
+    }
+
+    impl<T: SsqlEnum> EnumColumn for T {
+        fn to_column(&self) -> ColumnData<'static> {
+            SsqlEnum::to_column(self)
+        }
+
+        fn from_column(data: &ColumnData<'static>) -> SsqlResult<Self> {
+            <T as SsqlEnum>::from_column(data)?
+                .ok_or_else(|| "unexpected null value for non-nullable enum field".into())
+        }
+    }
+
+    impl<T: SsqlEnum> EnumColumn for Option<T> {
+        fn to_column(&self) -> ColumnData<'static> {
+            match self {
+                Some(v) => SsqlEnum::to_column(v),
+                None => T::null_column(),
+            }
+        }
+
+        fn from_column(data: &ColumnData<'static>) -> SsqlResult<Self> {
+            Ok(<T as SsqlEnum>::from_column(data)?)
+        }
+    }
+
+    /// Convert an enum field to a sql value.
+    pub fn to_sql<T: EnumColumn>(value: &T) -> ColumnData<'static> {
+        value.to_column()
+    }
+
+    /// Convert a sql value to an enum field.
+    pub fn from_sql<T: EnumColumn>(data: &ColumnData<'static>) -> SsqlResult<T> {
+        T::from_column(data)
+    }
+}
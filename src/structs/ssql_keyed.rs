@@ -0,0 +1,593 @@
+use tiberius::{Client, ToSql};
+use tokio::net::TcpStream;
+use tokio_util::compat::Compat;
+
+use crate::{ColExpr, QueryAble, SsqlError, SsqlMarker, SsqlResult};
+use crate::structs::bulk_insert;
+use crate::structs::table_scope::TableScope;
+use crate::utils::{quote_ident, SqlValue};
+
+/// Operations identifying rows by primary key, implemented by `#[derive(ORM)]` for structs
+/// with at least one `#[ssql(primary_key)]` field.
+/// Calling them on a struct without primary key fails to compile.
+/// ```compile_fail
+/// # use ssql::prelude::*;
+/// #[derive(ORM)]
+/// #[ssql(table = logs)]
+/// struct Log {
+///     message: String,
+/// }
+/// # async fn _test(mut conn: Client<Compat<TcpStream>>) -> SsqlResult<()> {
+/// Log { message: "a".to_string() }.update(&mut conn).await
+/// # }
+/// ```
+pub trait SsqlKeyed: SsqlMarker {
+    /// Find one item by its primary key, values are given in the order
+    /// the `#[ssql(primary_key)]` fields are declared.
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    ///  #[derive(ORM)]
+    ///  #[ssql(table = person_group)]
+    ///  struct PersonGroup{
+    ///     #[ssql(primary_key)]
+    ///     person_id: i32,
+    ///     #[ssql(primary_key)]
+    ///     group_id: i32,
+    ///  }
+    /// # async fn _test(mut conn: Client<Compat<TcpStream>>) -> SsqlResult<()> {
+    ///  let item = PersonGroup::find_by_pk(&[&1, &2], &mut conn).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// SQL: `SELECT ... FROM person_group WHERE person_group.person_id = 1 AND person_group.group_id = 2`
+    fn find_by_pk(
+        keys: &[&dyn ToSql],
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<Option<Self>>> + Send
+    where
+        Self: Sized + 'static,
+    {
+        async move { Self::find_by_pk_in(&TableScope::default(), keys, conn).await }
+    }
+
+    /// Same as [`find_by_pk`], from the table resolved in the given [`TableScope`].
+    ///
+    /// [`find_by_pk`]: trait.SsqlKeyed.html#method.find_by_pk
+    /// [`TableScope`]: struct.TableScope.html
+    fn find_by_pk_in(
+        scope: &TableScope,
+        keys: &[&dyn ToSql],
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<Option<Self>>> + Send
+    where
+        Self: Sized + 'static,
+    {
+        async move {
+            let pks = Self::primary_key_fields();
+            if pks.len() != keys.len() {
+                return Err(format!(
+                    "{} has {} primary key fields, got {} values",
+                    Self::table_name(),
+                    pks.len(),
+                    keys.len()
+                )
+                .into());
+            }
+            let mut query = Self::query().in_scope(scope.clone());
+            for (pk, key) in pks.into_iter().zip(keys) {
+                query = query.filter(Self::col(pk)?.eq(*key))?;
+            }
+            query.one(conn).await
+        }
+    }
+
+    /// Get one item by its single-column primary key, same as [`find_by_pk`] with one value.
+    ///
+    /// [`find_by_pk`]: trait.SsqlKeyed.html#method.find_by_pk
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    /// # #[derive(ORM)]
+    /// # #[ssql(table = person)]
+    /// # struct Person{
+    /// #    #[ssql(primary_key)]
+    /// #    id: i32,
+    /// #    email: Option<String>,
+    /// # }
+    /// # async fn _test(mut conn: Client<Compat<TcpStream>>) -> SsqlResult<()> {
+    ///  let person = Person::get(&1, &mut conn).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn get(
+        key: &dyn ToSql,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<Option<Self>>> + Send
+    where
+        Self: Sized + 'static,
+    {
+        async move { Self::find_by_pk(&[key], conn).await }
+    }
+
+    /// Same as [`get`], from the table resolved in the given [`TableScope`].
+    ///
+    /// [`get`]: trait.SsqlKeyed.html#method.get
+    /// [`TableScope`]: struct.TableScope.html
+    fn get_in(
+        scope: &TableScope,
+        key: &dyn ToSql,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<Option<Self>>> + Send
+    where
+        Self: Sized + 'static,
+    {
+        async move { Self::find_by_pk_in(scope, &[key], conn).await }
+    }
+
+    /// Get items by a list of single-column primary keys.
+    /// Lists over the parameter limit of SQL Server are expanded on server side, see [`InListStrategy`].
+    /// Items are returned in the order of the given keys, keys not found are skipped.
+    /// Keys must have the same type as the primary key field, as returned rows are matched back to them by value.
+    /// An error is returned for a row matching none of the keys, e.g. a key of another integer type
+    /// or a string differing only in case under a case-insensitive collation.
+    ///
+    /// [`InListStrategy`]: enum.InListStrategy.html
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    /// # #[derive(ORM)]
+    /// # #[ssql(table = person)]
+    /// # struct Person{
+    /// #    #[ssql(primary_key)]
+    /// #    id: i32,
+    /// #    email: Option<String>,
+    /// # }
+    /// # async fn _test(mut conn: Client<Compat<TcpStream>>) -> SsqlResult<()> {
+    ///  let persons = Person::get_many(&[&3, &1, &2], &mut conn).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn get_many(
+        keys: &[&dyn ToSql],
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<Vec<Self>>> + Send
+    where
+        Self: Sized + 'static,
+    {
+        async move { Self::get_many_in(&TableScope::default(), keys, conn).await }
+    }
+
+    /// Same as [`get_many`], from the table resolved in the given [`TableScope`].
+    ///
+    /// [`get_many`]: trait.SsqlKeyed.html#method.get_many
+    /// [`TableScope`]: struct.TableScope.html
+    fn get_many_in(
+        scope: &TableScope,
+        keys: &[&dyn ToSql],
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<Vec<Self>>> + Send
+    where
+        Self: Sized + 'static,
+    {
+        async move {
+            let pk = match Self::primary_key_fields().as_slice() {
+                [pk] => *pk,
+                pks => {
+                    return Err(format!(
+                        "get_many requires exactly one primary key field, {} has {}",
+                        Self::table_name(),
+                        pks.len()
+                    )
+                    .into())
+                }
+            };
+            let items = Self::query()
+                .in_scope(scope.clone())
+                .filter(Self::col(pk)?.is_in_ref(keys))?
+                .all(conn)
+                .await?;
+            let mut ret: Vec<(usize, Self)> = Vec::with_capacity(items.len());
+            for item in items {
+                let value = item.primary_key()[0].1.to_sql();
+                // keys of another type than the field, e.g. i64 for an int column, never compare equal
+                let pos = keys.iter().position(|k| k.to_sql() == value).ok_or_else(|| {
+                    format!(
+                        "{} returned primary key {:?} matching none of the keys given to get_many, \
+                         keys must have the type of field {}",
+                        Self::table_name(),
+                        value,
+                        pk
+                    )
+                })?;
+                ret.push((pos, item));
+            }
+            ret.sort_by_key(|(pos, _)| *pos);
+            Ok(ret.into_iter().map(|(_, item)| item).collect())
+        }
+    }
+
+    /// Update many items based on primary key in one round trip, returns number of affected rows.
+    /// Items are bulk loaded into a temp table and applied with one `UPDATE ... FROM` statement.
+    /// For structs with a `#[ssql(version)]` field, nothing is updated and [`SsqlError::ConcurrencyConflict`]
+    /// is returned if any row was modified since it was loaded.
+    ///
+    /// [`SsqlError::ConcurrencyConflict`]: enum.SsqlError.html#variant.ConcurrencyConflict
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    /// # #[derive(ORM)]
+    /// # #[ssql(table = person)]
+    /// # struct Person{
+    /// #    #[ssql(primary_key)]
+    /// #    id: i32,
+    /// #    email: Option<String>,
+    /// # }
+    /// # async fn _test(mut conn: Client<Compat<TcpStream>>) -> SsqlResult<u64> {
+    ///  let persons = (1..=1000).map(|id| Person { id, email: None });
+    ///  Person::update_many(persons, &mut conn).await
+    /// # }
+    /// ```
+    /// SQL: `UPDATE t SET email = s.email FROM person AS t INNER JOIN #staging AS s ON t.id = s.id`
+    fn update_many<I: IntoIterator<Item = Self> + Send>(
+        iter: I,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<u64>> + Send
+    where
+        I::IntoIter: Send,
+        Self: Sized,
+    {
+        async move { Self::update_many_in(&TableScope::default(), iter, conn).await }
+    }
+
+    /// Same as [`update_many`], in the table resolved in the given [`TableScope`].
+    ///
+    /// [`update_many`]: trait.SsqlKeyed.html#method.update_many
+    /// [`TableScope`]: struct.TableScope.html
+    fn update_many_in<I: IntoIterator<Item = Self> + Send>(
+        scope: &TableScope,
+        iter: I,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<u64>> + Send
+    where
+        I::IntoIter: Send,
+        Self: Sized,
+    {
+        async move { bulk_insert::update_many(iter, scope, conn).await }
+    }
+
+    /// Delete many items based on primary key in one round trip, returns number of affected rows.
+    /// Keys are bulk loaded into a temp table and applied with one `DELETE ... JOIN` statement.
+    /// See [`update_many`] for structs with a `#[ssql(version)]` field.
+    ///
+    /// [`update_many`]: trait.SsqlKeyed.html#method.update_many
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    /// # #[derive(ORM)]
+    /// # #[ssql(table = person)]
+    /// # struct Person{
+    /// #    #[ssql(primary_key)]
+    /// #    id: i32,
+    /// #    email: Option<String>,
+    /// # }
+    /// # async fn _test(mut conn: Client<Compat<TcpStream>>) -> SsqlResult<u64> {
+    ///  let persons = Person::query().all(&mut conn).await?;
+    ///  Person::delete_many(persons, &mut conn).await
+    /// # }
+    /// ```
+    /// SQL: `DELETE t FROM person AS t INNER JOIN #staging AS s ON t.id = s.id`
+    fn delete_many<I: IntoIterator<Item = Self> + Send>(
+        iter: I,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<u64>> + Send
+    where
+        I::IntoIter: Send,
+        Self: Sized,
+    {
+        async move { Self::delete_many_in(&TableScope::default(), iter, conn).await }
+    }
+
+    /// Same as [`delete_many`], from the table resolved in the given [`TableScope`].
+    ///
+    /// [`delete_many`]: trait.SsqlKeyed.html#method.delete_many
+    /// [`TableScope`]: struct.TableScope.html
+    fn delete_many_in<I: IntoIterator<Item = Self> + Send>(
+        scope: &TableScope,
+        iter: I,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<u64>> + Send
+    where
+        I::IntoIter: Send,
+        Self: Sized,
+    {
+        async move { bulk_insert::delete_many(iter, scope, conn).await }
+    }
+
+    /// Delete one item based on primary key, consume self.
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    ///  #[derive(ORM)]
+    ///  #[ssql(table = person)]
+    ///  struct Person{
+    ///     #[ssql(primary_key)]
+    ///     id: i32,
+    ///     email: Option<String>,
+    ///  }
+    ///  async fn _test(mut conn: Client<Compat<TcpStream>>) {
+    ///     let person = Person{id: 1,email: Some("a@gmail.com".to_string())};
+    ///     person.delete(&mut conn).await;
+    ///  }
+    /// ```
+    /// SQL: `DELETE FROM person WHERE id = 1`
+    fn delete(
+        self,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send
+    where
+        Self: Sized,
+    {
+        async move { self.delete_in(&TableScope::default(), conn).await }
+    }
+
+    /// Same as [`delete`], from the table resolved in the given [`TableScope`].
+    ///
+    /// [`delete`]: trait.SsqlKeyed.html#method.delete
+    /// [`TableScope`]: struct.TableScope.html
+    fn delete_in(
+        self,
+        scope: &TableScope,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send
+    where
+        Self: Sized;
+
+    /// Update one item based on primary key, borrow self.
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    ///  #[derive(ORM)]
+    ///  #[ssql(table = person)]
+    ///  struct Person{
+    ///      #[ssql(primary_key)]
+    ///     id: i32,
+    ///     email: Option<String>,
+    ///  }
+    ///  async fn _test(mut conn: Client<Compat<TcpStream>>) {
+    ///     let person = Person{id: 1,email: Some("a@gmail.com".to_string())};
+    ///     person.update(&mut conn).await;
+    ///  }
+    /// ```
+    /// SQL: `UPDATE person SET email = 'a@gmail.com' WHERE id = 1`
+    fn update(
+        &self,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send
+    where
+        Self: Sized,
+    {
+        async move { self.update_in(&TableScope::default(), conn).await }
+    }
+
+    /// Same as [`update`], in the table resolved in the given [`TableScope`].
+    ///
+    /// [`update`]: trait.SsqlKeyed.html#method.update
+    /// [`TableScope`]: struct.TableScope.html
+    fn update_in(
+        &self,
+        scope: &TableScope,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send
+    where
+        Self: Sized;
+
+    /// Update only the given columns of one item based on primary key, borrow self.
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    ///  #[derive(ORM)]
+    ///  #[ssql(table = person)]
+    ///  struct Person{
+    ///      #[ssql(primary_key)]
+    ///     id: i32,
+    ///     email: Option<String>,
+    ///     name: String,
+    ///  }
+    ///  async fn _test(mut conn: Client<Compat<TcpStream>>) -> SsqlResult<()> {
+    ///     let person = Person{id: 1, email: Some("a@gmail.com".to_string()), name: "a".to_string()};
+    ///     person.update_fields(&[Person::col("email")?], &mut conn).await
+    ///  }
+    /// ```
+    /// SQL: `UPDATE person SET email = 'a@gmail.com' WHERE id = 1`
+    fn update_fields(
+        &self,
+        columns: &[ColExpr],
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send
+    where
+        Self: Sized,
+    {
+        async move {
+            self.update_fields_in(&TableScope::default(), columns, conn)
+                .await
+        }
+    }
+
+    /// Same as [`update_fields`], in the table resolved in the given [`TableScope`].
+    ///
+    /// [`update_fields`]: trait.SsqlKeyed.html#method.update_fields
+    /// [`TableScope`]: struct.TableScope.html
+    fn update_fields_in(
+        &self,
+        scope: &TableScope,
+        columns: &[ColExpr],
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send
+    where
+        Self: Sized,
+    {
+        async move {
+            let mut fields = vec![];
+            for col in columns {
+                match col.table == Self::table_name() && Self::fields().contains(&col.field) {
+                    true if col.json_path.is_some() => {
+                        return Err(format!(
+                            "can not update {}, JSON values are updated with the whole column",
+                            col.full_column_name()
+                        )
+                        .into())
+                    }
+                    true if Self::readonly_fields().contains(&col.field) => {
+                        return Err(format!(
+                            "column {} is readonly",
+                            col.full_column_name()
+                        )
+                        .into())
+                    }
+                    true => fields.push(col.field),
+                    false => {
+                        return Err(format!(
+                            "column {} not found in {}",
+                            col.full_column_name(),
+                            Self::table_name()
+                        )
+                        .into())
+                    }
+                }
+            }
+            update_columns(self, scope, &fields, false, conn).await?;
+            Ok(())
+        }
+    }
+
+    /// Same as [`update`], but also refreshes the `#[ssql(version)]` field with the new row version,
+    /// so that the item can be updated again.
+    /// Returns [`SsqlError::ConcurrencyConflict`] if the row was modified since it was loaded.
+    ///
+    /// [`update`]: trait.SsqlKeyed.html#method.update
+    /// [`SsqlError::ConcurrencyConflict`]: enum.SsqlError.html#variant.ConcurrencyConflict
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    ///  #[derive(ORM)]
+    ///  #[ssql(table = person)]
+    ///  struct Person{
+    ///     #[ssql(primary_key)]
+    ///     id: i32,
+    ///     email: Option<String>,
+    ///     #[ssql(version)]
+    ///     row_ver: Vec<u8>,
+    ///  }
+    ///  async fn _test(mut conn: Client<Compat<TcpStream>>) -> SsqlResult<()> {
+    ///     if let Some(mut person) = Person::query().one(&mut conn).await? {
+    ///         person.email = None;
+    ///         person.update_refresh(&mut conn).await?;
+    ///         person.email = Some("a@gmail.com".to_string());
+    ///         person.update_refresh(&mut conn).await?;
+    ///     }
+    ///     Ok(())
+    ///  }
+    /// ```
+    fn update_refresh(
+        &mut self,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send
+    where
+        Self: Sized,
+    {
+        async move { self.update_refresh_in(&TableScope::default(), conn).await }
+    }
+
+    /// Same as [`update_refresh`], in the table resolved in the given [`TableScope`].
+    ///
+    /// [`update_refresh`]: trait.SsqlKeyed.html#method.update_refresh
+    /// [`TableScope`]: struct.TableScope.html
+    fn update_refresh_in(
+        &mut self,
+        scope: &TableScope,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send
+    where
+        Self: Sized,
+    {
+        async move {
+            let pks = Self::primary_key_fields();
+            let readonly = Self::readonly_fields();
+            let ver = self.version().map(|v| v.0);
+            let fields: Vec<&str> = Self::fields()
+                .into_iter()
+                .filter(|f| !pks.contains(f) && !readonly.contains(f) && Some(*f) != ver)
+                .collect();
+            if let Some(version) = update_columns(self, scope, &fields, true, conn).await? {
+                self.set_version(version);
+            }
+            Ok(())
+        }
+    }
+
+    #[doc(hidden)]
+    fn primary_key_fields() -> Vec<&'static str>
+    where
+        Self: Sized;
+
+    #[doc(hidden)]
+    fn primary_key(&self) -> Vec<(&'static str, &dyn ToSql)>;
+}
+
+/// Update the given columns of one item, checking the row version if the struct has one.
+/// With `refresh` set, the new row version is returned.
+async fn update_columns<T: SsqlKeyed>(
+    item: &T,
+    scope: &TableScope,
+    fields: &[&'static str],
+    refresh: bool,
+    conn: &mut Client<Compat<TcpStream>>,
+) -> SsqlResult<Option<Vec<u8>>> {
+    if fields.is_empty() {
+        return Ok(None);
+    }
+    let all_fields = T::fields();
+    let values: Vec<SqlValue> = item.field_values()?.into_iter().map(SqlValue).collect();
+    let mut params: Vec<&dyn ToSql> = vec![];
+    let mut set_clause = vec![];
+    for field in fields {
+        let idx = all_fields.iter().position(|f| f == field).unwrap();
+        params.push(&values[idx]);
+        set_clause.push(format!("{} = @p{}", quote_ident(field), params.len()));
+    }
+    let mut where_clause = vec![];
+    for (pk, dt) in item.primary_key().into_iter().chain(item.version()) {
+        params.push(dt);
+        where_clause.push(format!("{} = @p{}", quote_ident(pk), params.len()));
+    }
+    let where_clause = where_clause.join(" AND ");
+
+    let table = T::table_name();
+    let target = scope.resolve(table);
+    let set_clause = set_clause.join(", ");
+    let (affected, version) = match (refresh, item.version()) {
+        (true, Some((ver, _))) => {
+            let sql = format!(
+                "DECLARE @ver TABLE (v BINARY(8)); \
+                UPDATE {target} SET {set_clause} OUTPUT INSERTED.{} INTO @ver WHERE {where_clause}; \
+                SELECT v FROM @ver",
+                quote_ident(ver)
+            );
+            let rows = conn
+                .query(sql.as_str(), params.as_slice())
+                .await
+                .map_err(|e| SsqlError::with_sql(e, &sql))?
+                .into_first_result()
+                .await?;
+            let version = rows
+                .first()
+                .and_then(|r| r.get::<&[u8], _>(0))
+                .map(|v| v.to_vec());
+            (rows.len() as u64, version)
+        }
+        _ => {
+            let sql = format!("UPDATE {target} SET {set_clause} WHERE {where_clause}");
+            let ret = conn
+                .execute(sql.as_str(), params.as_slice())
+                .await
+                .map_err(|e| SsqlError::with_sql(e, &sql))?;
+            (ret.total(), None)
+        }
+    };
+    if affected == 0 && item.version().is_some() {
+        return Err(SsqlError::ConcurrencyConflict(table.to_string()));
+    }
+    Ok(version)
+}
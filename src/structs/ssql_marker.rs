@@ -2,15 +2,17 @@
 use polars::prelude::*;
 #[cfg(feature = "serde")]
 use serde_json::{Map, Value};
-use tiberius::{Client, ToSql};
+use tiberius::{Client, ColumnData, ToSql};
 #[cfg(feature = "polars")]
 use tiberius::QueryStream;
 use tokio::net::TcpStream;
 use tokio_util::compat::Compat;
 
-use crate::{ColExpr, QueryBuilderI, SsqlResult};
+use crate::{ColExpr, QueryBuilderI, SsqlError, SsqlResult};
+use crate::structs::bulk_insert::{self, BulkOptions};
 use crate::structs::query_core::QueryCore;
 use crate::structs::raw_query_builder::RawQueryBuilder;
+use crate::structs::table_scope::TableScope;
 
 /// a trait automatically derived via `#[derive(ORM)]` macro, all these methods are available.
 pub trait SsqlMarker: Send + Sync {
@@ -23,12 +25,22 @@ pub trait SsqlMarker: Send + Sync {
     where
         Self: Sized;
     #[doc(hidden)]
+    fn readonly_fields() -> Vec<&'static str>
+    where
+        Self: Sized;
+    #[doc(hidden)]
     #[cfg(feature = "serde")]
-    fn row_to_json(row: &tiberius::Row) -> Map<String, Value>
+    fn row_to_json(row: &tiberius::Row) -> SsqlResult<Map<String, Value>>
     where
         Self: Sized;
     #[doc(hidden)]
-    fn row_to_struct(row: &tiberius::Row) -> Self
+    fn row_to_struct(row: &tiberius::Row) -> SsqlResult<Self>
+    where
+        Self: Sized;
+
+    #[doc(hidden)]
+    #[cfg(feature = "polars")]
+    fn dataframe_from_rows(rows: &[tiberius::Row]) -> SsqlResult<DataFrame>
     where
         Self: Sized;
 
@@ -38,7 +50,10 @@ pub trait SsqlMarker: Send + Sync {
         stream: QueryStream<'a>,
     ) -> impl std::future::Future<Output = SsqlResult<DataFrame>> + Send
     where
-        Self: Sized;
+        Self: Sized,
+    {
+        async move { Self::dataframe_from_rows(&stream.into_first_result().await?) }
+    }
 
     /// Generate a query builder for the struct.
     fn query<'a>() -> QueryBuilderI<'a, Self>
@@ -66,18 +81,23 @@ pub trait SsqlMarker: Send + Sync {
     where
         Self: Sized,
     {
-        let mut q = QueryCore::default();
-        q.raw_sql = Some(sql.to_string());
-        for p in params {
-            q.query_params.push(*p);
-        }
         RawQueryBuilder {
-            core: q,
+            core: QueryCore::raw(sql, params),
             t: Default::default(),
         }
     }
 
+    #[doc(hidden)]
+    fn field_values(&self) -> SsqlResult<Vec<ColumnData<'_>>>;
+
+    #[doc(hidden)]
+    fn into_columns_data(self) -> SsqlResult<Vec<ColumnData<'static>>>
+    where
+        Self: Sized;
+
     /// Bulk insert, takes everything that can be turned into iterator that generate specific structs.
+    /// Fields are matched to table columns by name, identity and computed columns are skipped.
+    /// An error listing mismatched columns is returned if struct and table do not correspond.
     /// ```no_run
     /// # use ssql::prelude::*;
     /// # #[derive(ORM)]
@@ -106,7 +126,41 @@ pub trait SsqlMarker: Send + Sync {
     ) -> impl std::future::Future<Output = SsqlResult<u64>> + Send
     where
         I::IntoIter: Send,
-        Self: Sized;
+        Self: Sized,
+    {
+        async move { bulk_insert::insert_many(iter, &BulkOptions::default(), conn).await }
+    }
+
+    /// Same as [`insert_many`], with batching, hints and progress reporting configured by [`BulkOptions`].
+    ///
+    /// [`insert_many`]: trait.SsqlMarker.html#method.insert_many
+    /// [`BulkOptions`]: struct.BulkOptions.html
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    /// # #[derive(ORM)]
+    /// # #[ssql(table = person)]
+    /// # struct Person{
+    /// #    id: i32,
+    /// #    email: Option<String>,
+    /// # }
+    /// # async fn insert(mut conn: Client<Compat<TcpStream>>) -> SsqlResult<u64> {
+    ///     let options = BulkOptions::new()
+    ///         .batch_size(10_000)
+    ///         .on_progress(|p| println!("{} rows inserted", p.total_rows));
+    ///     Person::insert_many_with((1..=1_000_000).map(|id| Person { id, email: None }), &options, &mut conn).await
+    /// # }
+    /// ```
+    fn insert_many_with<I: IntoIterator<Item = Self> + Send>(
+        iter: I,
+        options: &BulkOptions,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<u64>> + Send
+    where
+        I::IntoIter: Send,
+        Self: Sized,
+    {
+        async move { bulk_insert::insert_many(iter, options, conn).await }
+    }
 
     /// Insert one item, consume self.
     /// ```no_run
@@ -126,13 +180,44 @@ pub trait SsqlMarker: Send + Sync {
     fn insert(
         self,
         conn: &mut Client<Compat<TcpStream>>,
-    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send;
+    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send
+    where
+        Self: Sized,
+    {
+        async move { self.insert_in(&TableScope::default(), conn).await }
+    }
+
+    /// Same as [`insert`], into the table resolved in the given [`TableScope`].
+    ///
+    /// [`insert`]: trait.SsqlMarker.html#method.insert
+    /// [`TableScope`]: struct.TableScope.html
+    /// ```no_run
+    /// # use ssql::prelude::*;
+    /// # #[derive(ORM)]
+    /// # #[ssql(table = person)]
+    /// # struct Person{
+    /// #    id: i32,
+    /// #    email: Option<String>,
+    /// # }
+    /// # async fn insert(mut conn: Client<Compat<TcpStream>>) -> SsqlResult<()> {
+    ///  let person = Person{id: 1,email: Some("a@gmail.com".to_string())};
+    ///  person.insert_in(&TableScope::schema("tenant42"), &mut conn).await
+    /// # }
+    /// ```
+    /// SQL: `INSERT INTO [tenant42].[person] ([id], [email]) VALUES ( 1, 'a@gmail.com')`
+    fn insert_in(
+        self,
+        scope: &TableScope,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send
+    where
+        Self: Sized;
 
     /// Insert one item while ignoring the primary key.
     /// Specified for those using `Identity` or `Auto-Increment` as primary key.
     /// If primary key is not set, this fn will perform as same as [`insert`]
     ///
-    /// [`insert`]: trait.SsqlMarker.html#tymethod.insert
+    /// [`insert`]: trait.SsqlMarker.html#method.insert
     ///
     /// ```no_run
     /// # use ssql::prelude::*;
@@ -152,74 +237,66 @@ pub trait SsqlMarker: Send + Sync {
     fn insert_ignore_pk(
         self,
         conn: &mut Client<Compat<TcpStream>>,
-    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send where Self: Sized;
+    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send
+    where
+        Self: Sized,
+    {
+        async move { self.insert_ignore_pk_in(&TableScope::default(), conn).await }
+    }
 
-    /// Delete one item based on primary key, consume self.
-    /// Will panic if primary key is not set.
-    /// ```no_run
-    /// # use ssql::prelude::*;
-    ///  #[derive(ORM)]
-    ///  #[ssql(table = person)]
-    ///  struct Person{
-    ///     #[ssql(primary_key)]
-    ///     id: i32,
-    ///     email: Option<String>,
-    ///  }
-    ///  async fn _test(mut conn: Client<Compat<TcpStream>>) {
-    ///     let person = Person{id: 1,email: Some("a@gmail.com".to_string())};
-    ///     person.delete(&mut conn).await;
-    ///  }
-    /// ```
-    /// SQL: `DELETE FROM person WHERE id = 1`
-    fn delete(
+    /// Same as [`insert_ignore_pk`], into the table resolved in the given [`TableScope`].
+    ///
+    /// [`insert_ignore_pk`]: trait.SsqlMarker.html#method.insert_ignore_pk
+    /// [`TableScope`]: struct.TableScope.html
+    fn insert_ignore_pk_in(
         self,
+        scope: &TableScope,
         conn: &mut Client<Compat<TcpStream>>,
-    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send where Self: Sized;
-
-    /// Update one item based on primary key, borrow self.
-    /// Will panic if primary key is not set.
-    /// ```no_run
-    /// # use ssql::prelude::*;
-    ///  #[derive(ORM)]
-    ///  #[ssql(table = person)]
-    ///  struct Person{
-    ///      #[ssql(primary_key)]
-    ///     id: i32,
-    ///     email: Option<String>,
-    ///  }
-    ///  async fn _test(mut conn: Client<Compat<TcpStream>>) {
-    ///     let person = Person{id: 1,email: Some("a@gmail.com".to_string())};
-    ///     person.update(&mut conn).await;
-    ///  }
-    /// ```
-    /// SQL: `UPDATE person SET email = 'a@gmail.com' WHERE id = 1`
-    fn update(
-        &self,
-        conn: &mut Client<Compat<TcpStream>>,
-    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send where Self: Sized;
+    ) -> impl std::future::Future<Output = SsqlResult<()>> + Send
+    where
+        Self: Sized;
 
     #[doc(hidden)]
-    fn relationship(input: &str) -> &'static str
+    fn relationship(input: &str) -> Option<&'static str>
     where
         Self: Sized;
 
     #[doc(hidden)]
-    fn primary_key(&self) -> (&'static str, &dyn ToSql);
+    fn version(&self) -> Option<(&'static str, &dyn ToSql)>;
+
+    #[doc(hidden)]
+    fn set_version(&mut self, version: Vec<u8>);
+
     /// Generate a Column Expression that can be used in filtering and ordering.
     /// This method will failed if the given column name is no present in the struct.
     /// Thus it returns [`SsqlResult`]
     ///
+    /// A path following the column, as in `payload.$.customer.id`, refers to a value inside a JSON column,
+    /// same as [`ColExpr::json`].
+    ///
     /// [`SsqlResult`]: type.SsqlResult.html
+    /// [`ColExpr::json`]: struct.ColExpr.html#method.json
     fn col(field: &'static str) -> SsqlResult<ColExpr>
     where
         Self: Sized,
     {
+        let (field, json_path) = match field.split_once(".$") {
+            Some((field, path)) => (field, Some(format!("${}", path))),
+            None => (field, None),
+        };
         match Self::fields().contains(&field) {
-            true => Ok(ColExpr {
-                table: Self::table_name(),
-                field,
+            true => {
+                let col = ColExpr::new(Self::table_name(), field);
+                Ok(match json_path {
+                    Some(path) => col.json(&path),
+                    None => col,
+                })
+            }
+            false => Err(SsqlError::ColumnNotFound {
+                table: Self::table_name().to_string(),
+                column: field.to_string(),
             }),
-            false => Err(format!("column {} not found in {}", field, Self::table_name()).into()),
         }
     }
 }
+
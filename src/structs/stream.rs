@@ -1,21 +1,48 @@
+use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use futures_lite::{ready, Stream};
 use futures_lite::StreamExt;
+use futures_util::TryStreamExt;
 use tiberius::{QueryItem, QueryStream, Row};
 
+use crate::SsqlResult;
 
-/// stream
+
+// decodes a row into an item of the stream
+type RowDecoder<T> = Box<dyn for<'b> Fn(&'b Row) -> SsqlResult<T> + Send>;
+
+/// Stream of rows decoded into `T`, returned by [`stream`].
+/// Errors of the server, such as a deadlock or timeout in the middle of the result set,
+/// and rows that can not be decoded are yielded as `Err`, it thus works with `TryStream` combinators as well.
+///
+/// [`stream`]: trait.QueryAble.html#method.stream
+/// ```no_run
+/// # use ssql::prelude::*;
+/// use futures_lite::StreamExt;
+/// # #[derive(ORM)]
+/// # #[ssql(table = person)]
+/// # struct Person {
+/// #     id: i32,
+/// # }
+/// async fn _test(conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<()> {
+///     let mut stream = Person::query().stream(conn).await?.chunks(1000);
+///     while let Some(chunk) = stream.next().await {
+///         let persons: Vec<Person> = chunk?;
+///     }
+///     Ok(())
+/// }
+/// ```
 pub struct RowStream<'a, T> {
     query_stream: QueryStream<'a>,
-    func: Box<dyn for<'b> Fn(&'b Row) -> T + Send>,
+    func: RowDecoder<T>,
 }
 
 impl<'a, T> RowStream<'a, T> {
     pub(crate) fn new<F>(stream: QueryStream<'a>, func: F) -> Self
     where
-        F: 'static + for<'b> Fn(&'b Row) -> T + Send,
+        F: 'static + for<'b> Fn(&'b Row) -> SsqlResult<T> + Send,
     {
         Self {
             query_stream: stream,
@@ -24,8 +51,41 @@ impl<'a, T> RowStream<'a, T> {
     }
 }
 
+impl<'a, T: Unpin> RowStream<'a, T> {
+    /// Collect all rows, stopping at the first error.
+    pub async fn try_collect(self) -> SsqlResult<Vec<T>> {
+        TryStreamExt::try_collect(self).await
+    }
+
+    /// Group rows in vectors of `size` rows, the last one holding the remaining rows.
+    /// Rows read before an error are dropped along with the incomplete chunk.
+    ///
+    /// # Panics
+    /// Panics if `size` is zero.
+    pub fn chunks(self, size: usize) -> impl Stream<Item = SsqlResult<Vec<T>>> + 'a
+    where
+        T: 'a,
+    {
+        self.try_chunks(size).map_err(|e| e.1)
+    }
+
+    /// Run `func` on each row with at most `limit` calls running at the same time, `None` meaning no limit.
+    /// Stops at the first error, either of the stream or returned by `func`.
+    pub async fn for_each_concurrent<F, Fut>(
+        self,
+        limit: impl Into<Option<usize>>,
+        func: F,
+    ) -> SsqlResult<()>
+    where
+        F: FnMut(T) -> Fut,
+        Fut: Future<Output = SsqlResult<()>>,
+    {
+        self.try_for_each_concurrent(limit, func).await
+    }
+}
+
 impl<'a, T: Unpin> Stream for RowStream<'a, T> {
-    type Item = T;
+    type Item = SsqlResult<T>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.get_mut();
@@ -34,7 +94,10 @@ impl<'a, T: Unpin> Stream for RowStream<'a, T> {
                 None => {
                     return Poll::Ready(None);
                 }
-                Some(v) => match v.unwrap() {
+                Some(Err(e)) => {
+                    return Poll::Ready(Some(Err(e.into())));
+                }
+                Some(Ok(v)) => match v {
                     QueryItem::Row(v) => {
                         return Poll::Ready(Some((this.func)(&v)));
                     }
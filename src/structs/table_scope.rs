@@ -0,0 +1,55 @@
+use crate::utils::quote_ident;
+
+/// Schema and database that tables are resolved in, overriding the ones set in `#[ssql(...)]`.
+/// Useful when tenants live in separate schemas or databases with identical tables.
+/// The default scope keeps the names as derived.
+/// ```
+/// # use ssql::prelude::*;
+/// let scope = TableScope::schema("tenant42");
+/// let scope = TableScope::schema("tenant42").database("archive");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableScope {
+    pub(crate) database: Option<String>,
+    pub(crate) schema: Option<String>,
+}
+
+impl TableScope {
+    /// Resolve tables in the given schema.
+    pub fn schema(schema: &str) -> Self {
+        Self {
+            database: None,
+            schema: Some(schema.to_string()),
+        }
+    }
+
+    /// Resolve tables in the given database, resulting in three-part names `[database].[schema].[table]`.
+    pub fn database(mut self, database: &str) -> Self {
+        self.database = Some(database.to_string());
+        self
+    }
+
+    /// Quoted name of the table in this scope, `table` being the name a struct is derived with.
+    /// ```
+    /// # use ssql::prelude::*;
+    /// assert_eq!(TableScope::schema("tenant42").resolve("dbo.Person"), "[tenant42].[Person]");
+    /// ```
+    pub fn resolve(&self, table: &str) -> String {
+        let mut parts: Vec<&str> = table.split('.').collect();
+        let name = parts.pop().unwrap_or_default();
+        let schema = self.schema.as_deref().or(parts.pop());
+        let database = self.database.as_deref().or(parts.pop());
+        match (database, schema) {
+            (None, None) => quote_ident(name),
+            (None, Some(s)) => format!("{}.{}", quote_ident(s), quote_ident(name)),
+            // default schema of the database
+            (Some(d), None) => format!("{}..{}", quote_ident(d), quote_ident(name)),
+            (Some(d), Some(s)) => format!(
+                "{}.{}.{}",
+                quote_ident(d),
+                quote_ident(s),
+                quote_ident(name)
+            ),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::ops::{Deref, DerefMut};
+
+use tiberius::Client;
+use tokio::net::TcpStream;
+use tokio_util::compat::Compat;
+
+use crate::{ColExpr, SsqlKeyed, SsqlMarker, SsqlResult, TableScope};
+
+/// Wrapper recording which fields of a table struct were modified since it was loaded,
+/// so that [`update`] only writes those columns.
+/// The wrapped struct is accessible through `Deref` and `DerefMut`.
+///
+/// [`update`]: struct.Tracked.html#method.update
+/// ```no_run
+/// # use ssql::prelude::*;
+/// # #[derive(ORM, Clone)]
+/// # #[ssql(table = person)]
+/// # struct Person{
+/// #    #[ssql(primary_key)]
+/// #    id: i32,
+/// #    email: Option<String>,
+/// # }
+/// # async fn _test(mut conn: Client<Compat<TcpStream>>) -> SsqlResult<()> {
+/// if let Some(person) = Person::query().one(&mut conn).await? {
+///     let mut person = Tracked::new(person);
+///     person.email = Some("b@gmail.com".to_string());
+///     // SQL: `UPDATE person SET email = 'b@gmail.com' WHERE id = 1`
+///     person.update(&mut conn).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Tracked<T>
+where
+    T: SsqlMarker + Clone,
+{
+    inner: T,
+    original: T,
+}
+
+impl<T> Tracked<T>
+where
+    T: SsqlMarker + Clone,
+{
+    /// Start tracking changes made to the given item.
+    pub fn new(inner: T) -> Self {
+        Self {
+            original: inner.clone(),
+            inner,
+        }
+    }
+
+    /// Columns whose value differs from the one at load or last update, readonly columns are ignored.
+    /// All columns are reported if the values can not be converted, so that [`update`] returns the error.
+    ///
+    /// [`update`]: struct.Tracked.html#method.update
+    pub fn changed_columns(&self) -> Vec<ColExpr> {
+        let readonly = T::readonly_fields();
+        let (current, original) = match (self.inner.field_values(), self.original.field_values()) {
+            (Ok(current), Ok(original)) => (current, original),
+            _ => (vec![], vec![]),
+        };
+        T::fields()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, field)| !readonly.contains(field))
+            .filter(|(idx, _)| current.get(*idx).is_none() || current.get(*idx) != original.get(*idx))
+            .map(|(_, field)| ColExpr::new(T::table_name(), field))
+            .collect()
+    }
+
+    /// Whether any field was modified.
+    pub fn is_dirty(&self) -> bool {
+        !self.changed_columns().is_empty()
+    }
+
+    /// Stop tracking and return the wrapped item.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T> Tracked<T>
+where
+    T: SsqlKeyed + Clone,
+{
+    /// Update modified columns based on primary key, does nothing if no field was modified.
+    pub async fn update(&mut self, conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<()> {
+        self.update_in(&TableScope::default(), conn).await
+    }
+
+    /// Same as [`update`], in the table resolved in the given [`TableScope`].
+    ///
+    /// [`update`]: struct.Tracked.html#method.update
+    /// [`TableScope`]: struct.TableScope.html
+    pub async fn update_in(
+        &mut self,
+        scope: &TableScope,
+        conn: &mut Client<Compat<TcpStream>>,
+    ) -> SsqlResult<()> {
+        let columns = self.changed_columns();
+        self.inner.update_fields_in(scope, &columns, conn).await?;
+        self.original = self.inner.clone();
+        Ok(())
+    }
+}
+
+impl<T> From<T> for Tracked<T>
+where
+    T: SsqlMarker + Clone,
+{
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T> Deref for Tracked<T>
+where
+    T: SsqlMarker + Clone,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for Tracked<T>
+where
+    T: SsqlMarker + Clone,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
@@ -1,4 +1,6 @@
-use tiberius::Client;
+use tiberius::{Client, ColumnData, FromSql, Row, ToSql};
+
+use crate::{SsqlError, SsqlResult};
 use tokio::net::TcpStream;
 use tokio_util::compat::TokioAsyncWriteCompatExt;
 
@@ -20,3 +22,176 @@ pub async fn get_client(
     let client = Client::connect(config, tcp.compat_write()).await.unwrap();
     client
 }
+
+/// Quote an identifier with brackets, escaping `]`, e.g. `Order Date` becomes `[Order Date]`.
+pub fn quote_ident(name: &str) -> String {
+    format!("[{}]", name.replace(']', "]]"))
+}
+
+/// Quote a multi-part name such as `schema.table` part by part, e.g. `[schema].[table]`.
+/// Each part is expected not to contain `.`.
+pub fn quote_name(name: &str) -> String {
+    name.split('.').map(quote_ident).collect::<Vec<_>>().join(".")
+}
+
+/// A value already converted for SQL Server, usable as query parameter.
+/// Fields with `#[ssql(with = "...")]` are sent as such values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlValue<'a>(pub ColumnData<'a>);
+
+impl ToSql for SqlValue<'_> {
+    fn to_sql(&self) -> ColumnData<'_> {
+        self.0.clone()
+    }
+}
+
+/// Value of the named column in a row, `None` if the row has no such column.
+pub fn column_data<'a>(row: &'a Row, name: &str) -> Option<&'a ColumnData<'static>> {
+    row.cells()
+        .find(|(col, _)| col.name() == name)
+        .map(|(_, data)| data)
+}
+
+/// Value of a column read into a field of type `rust_type`, erroring instead of panicking
+/// when the column is missing or holds a value of another type.
+#[doc(hidden)]
+pub fn decode<'a, T: FromSql<'a>>(
+    row: &'a Row,
+    table: &str,
+    name: &str,
+    rust_type: &str,
+) -> SsqlResult<Option<T>> {
+    match row.try_get::<T, &str>(name) {
+        Ok(v) => Ok(v),
+        Err(_) => Err(decode_error(row, table, name, rust_type)),
+    }
+}
+
+/// Value of a non-nullable field, erroring on `NULL`.
+#[doc(hidden)]
+pub fn require<T>(value: Option<T>, row: &Row, table: &str, name: &str, rust_type: &str) -> SsqlResult<T> {
+    value.ok_or_else(|| decode_error(row, table, name, rust_type))
+}
+
+/// Value of a field with custom conversion, the error of the conversion being reported with the column.
+#[doc(hidden)]
+pub fn decode_with<T>(
+    row: &Row,
+    table: &str,
+    name: &str,
+    from_sql: impl FnOnce(&ColumnData<'static>) -> SsqlResult<T>,
+) -> SsqlResult<T> {
+    let data = column_data(row, name).ok_or_else(|| decode_error(row, table, name, ""))?;
+    from_sql(data).map_err(|e| SsqlError::Decode {
+        table: table.to_string(),
+        column: column_of(table, name).to_string(),
+        expected: std::any::type_name::<T>().to_string(),
+        found: e.to_string(),
+    })
+}
+
+fn decode_error(row: &Row, table: &str, name: &str, rust_type: &str) -> SsqlError {
+    let column = column_of(table, name).to_string();
+    let table = table.to_string();
+    let found = row
+        .cells()
+        .find(|(col, _)| col.name() == name)
+        .map(|(col, data)| match is_null(data) {
+            true => "NULL".to_string(),
+            false => format!("{:?}", col.column_type()),
+        });
+    match found {
+        Some(found) => SsqlError::Decode {
+            table,
+            column,
+            expected: rust_type.to_string(),
+            found,
+        },
+        None => SsqlError::ColumnNotFound { table, column },
+    }
+}
+
+pub(crate) fn is_null(data: &ColumnData<'static>) -> bool {
+    match data {
+        ColumnData::U8(v) => v.is_none(),
+        ColumnData::I16(v) => v.is_none(),
+        ColumnData::I32(v) => v.is_none(),
+        ColumnData::I64(v) => v.is_none(),
+        ColumnData::F32(v) => v.is_none(),
+        ColumnData::F64(v) => v.is_none(),
+        ColumnData::Bit(v) => v.is_none(),
+        ColumnData::String(v) => v.is_none(),
+        ColumnData::Guid(v) => v.is_none(),
+        ColumnData::Binary(v) => v.is_none(),
+        ColumnData::Numeric(v) => v.is_none(),
+        ColumnData::Xml(v) => v.is_none(),
+        ColumnData::DateTime(v) => v.is_none(),
+        ColumnData::SmallDateTime(v) => v.is_none(),
+        ColumnData::Time(v) => v.is_none(),
+        ColumnData::Date(v) => v.is_none(),
+        ColumnData::DateTime2(v) => v.is_none(),
+        ColumnData::DateTimeOffset(v) => v.is_none(),
+    }
+}
+
+// column name without the table used to alias it in query builders
+fn column_of<'a>(table: &str, name: &'a str) -> &'a str {
+    name.strip_prefix(table)
+        .and_then(|n| n.strip_prefix('.'))
+        .unwrap_or(name)
+}
+
+/// Text representation of a value, `None` being a null value.
+/// Binary values are written in hex, date and time values in ISO 8601 if feature `chrono` is enabled.
+pub fn column_text(data: &ColumnData<'static>) -> Option<String> {
+    match data {
+        ColumnData::U8(v) => v.map(|v| v.to_string()),
+        ColumnData::I16(v) => v.map(|v| v.to_string()),
+        ColumnData::I32(v) => v.map(|v| v.to_string()),
+        ColumnData::I64(v) => v.map(|v| v.to_string()),
+        ColumnData::F32(v) => v.map(|v| v.to_string()),
+        ColumnData::F64(v) => v.map(|v| v.to_string()),
+        ColumnData::Bit(v) => v.map(|v| v.to_string()),
+        ColumnData::String(v) => v.as_ref().map(|v| v.to_string()),
+        ColumnData::Guid(v) => v.map(|v| v.to_string()),
+        ColumnData::Binary(v) => v
+            .as_ref()
+            .map(|v| v.iter().map(|b| format!("{:02X}", b)).collect()),
+        ColumnData::Numeric(v) => v.map(|v| v.to_string()),
+        ColumnData::Xml(v) => v.as_ref().map(|v| v.to_string()),
+        ColumnData::DateTime(_)
+        | ColumnData::SmallDateTime(_)
+        | ColumnData::Time(_)
+        | ColumnData::Date(_)
+        | ColumnData::DateTime2(_)
+        | ColumnData::DateTimeOffset(_) => date_text(data),
+    }
+}
+
+#[cfg(feature = "chrono")]
+fn date_text(data: &ColumnData<'static>) -> Option<String> {
+    use tiberius::FromSql;
+    match data {
+        ColumnData::Date(_) => chrono::NaiveDate::from_sql(data).ok()?.map(|v| v.to_string()),
+        ColumnData::Time(_) => chrono::NaiveTime::from_sql(data).ok()?.map(|v| v.to_string()),
+        ColumnData::DateTimeOffset(_) => chrono::DateTime::<chrono::Utc>::from_sql(data)
+            .ok()?
+            .map(|v| v.to_rfc3339()),
+        _ => chrono::NaiveDateTime::from_sql(data)
+            .ok()?
+            .map(|v| v.format("%Y-%m-%dT%H:%M:%S%.f").to_string()),
+    }
+}
+
+#[cfg(not(feature = "chrono"))]
+fn date_text(data: &ColumnData<'static>) -> Option<String> {
+    match data {
+        ColumnData::DateTime(v) => v.map(|v| format!("{:?}", v)),
+        ColumnData::SmallDateTime(v) => v.map(|v| format!("{:?}", v)),
+        ColumnData::Time(v) => v.map(|v| format!("{:?}", v)),
+        ColumnData::Date(v) => v.map(|v| format!("{:?}", v)),
+        ColumnData::DateTime2(v) => v.map(|v| format!("{:?}", v)),
+        ColumnData::DateTimeOffset(v) => v.map(|v| format!("{:?}", v)),
+        _ => None,
+    }
+}
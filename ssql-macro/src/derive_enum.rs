@@ -0,0 +1,217 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::Data::Enum;
+use syn::{DataEnum, DeriveInput, Expr, ExprLit, Ident, Lit, Meta};
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+
+pub struct DeriveSsqlEnum<'a> {
+    enum_ident: &'a Ident,
+    // integer type the enum is stored as, stored as string if not set
+    repr: Option<Ident>,
+    // variant and its code when stored as string
+    variants: Vec<(&'a Ident, String)>,
+}
+
+impl<'a> DeriveSsqlEnum<'a> {
+    pub(crate) fn new(ast: &'a DeriveInput) -> syn::Result<Self> {
+        let variants = match &ast.data {
+            Enum(DataEnum { variants, .. }) => variants,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &ast.ident,
+                    "SsqlEnum can only be derived for enums",
+                ))
+            }
+        };
+        let repr = match parse_str_attr(&ast.attrs, "repr")? {
+            Some((r, span)) => match r.as_str() {
+                "u8" | "i16" | "i32" | "i64" => Some(Ident::new(&r, proc_macro2::Span::call_site())),
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        span,
+                        "repr must be one of u8, i16, i32, i64",
+                    ))
+                }
+            },
+            None => None,
+        };
+        let variants = variants
+            .iter()
+            .map(|v| {
+                if !v.fields.is_empty() {
+                    return Err(syn::Error::new_spanned(
+                        v,
+                        "SsqlEnum variants can not have fields",
+                    ));
+                }
+                let code = parse_str_attr(&v.attrs, "rename")?
+                    .map(|(c, _)| c)
+                    .unwrap_or(v.ident.to_string());
+                Ok((&v.ident, code))
+            })
+            .collect::<syn::Result<_>>()?;
+        Ok(Self {
+            enum_ident: &ast.ident,
+            repr,
+            variants,
+        })
+    }
+
+    pub(crate) fn expand(self) -> proc_macro::TokenStream {
+        let enum_ident = self.enum_ident;
+        let enum_name = enum_ident.to_string();
+        let idents: Vec<_> = self.variants.iter().map(|(v, _)| v).collect();
+
+        let (to_column, null_column, from_column) = match &self.repr {
+            Some(repr) => {
+                let variant = column_variant(repr);
+                (
+                    quote! {
+                        let code = match self {
+                            #(Self::#idents => Self::#idents as i64,)*
+                        };
+                        ColumnData::#variant(Some(code as #repr))
+                    },
+                    quote! { ColumnData::#variant(None) },
+                    quote! {
+                        let code: i64 = match data {
+                            ColumnData::U8(v) => match v { Some(v) => *v as i64, None => return Ok(None) },
+                            ColumnData::I16(v) => match v { Some(v) => *v as i64, None => return Ok(None) },
+                            ColumnData::I32(v) => match v { Some(v) => *v as i64, None => return Ok(None) },
+                            ColumnData::I64(v) => match v { Some(v) => *v, None => return Ok(None) },
+                            v => return Err(tiberius::error::Error::Conversion(
+                                format!("cannot interpret {:?} as {}", v, #enum_name).into()
+                            )),
+                        };
+                        #(if code == Self::#idents as i64 {
+                            return Ok(Some(Self::#idents));
+                        })*
+                        Err(tiberius::error::Error::Conversion(
+                            format!("{} is not a valid {}", code, #enum_name).into()
+                        ))
+                    },
+                )
+            }
+            None => {
+                let codes: Vec<_> = self.variants.iter().map(|(_, c)| c).collect();
+                (
+                    quote! {
+                        let code = match self {
+                            #(Self::#idents => #codes,)*
+                        };
+                        ColumnData::String(Some(code.into()))
+                    },
+                    quote! { ColumnData::String(None) },
+                    quote! {
+                        match data {
+                            ColumnData::String(None) => Ok(None),
+                            ColumnData::String(Some(code)) => match code.as_ref() {
+                                #(#codes => Ok(Some(Self::#idents)),)*
+                                code => Err(tiberius::error::Error::Conversion(
+                                    format!("{} is not a valid {}", code, #enum_name).into()
+                                )),
+                            },
+                            v => Err(tiberius::error::Error::Conversion(
+                                format!("cannot interpret {:?} as {}", v, #enum_name).into()
+                            )),
+                        }
+                    },
+                )
+            }
+        };
+
+        // DataFrame columns hold the stored codes as text
+        #[cfg(feature = "polars")]
+        let to_series = quote! {
+            impl ssql::ToSeries for #enum_ident {
+                fn to_series(name: &str, values: Vec<Option<Self>>) -> PolarsResult<Series> {
+                    let values: Vec<Option<String>> = values
+                        .iter()
+                        .map(|v| {
+                            v.as_ref()
+                                .and_then(|v| ssql::utils::column_text(&ssql::SsqlEnum::to_column(v)))
+                        })
+                        .collect();
+                    <String as ssql::ToSeries>::to_series(name, values)
+                }
+            }
+        };
+        #[cfg(not(feature = "polars"))]
+        let to_series = quote! {};
+
+        quote! {
+            #to_series
+
+            impl ssql::SsqlEnum for #enum_ident {
+                fn to_column(&self) -> ColumnData<'static> {
+                    #to_column
+                }
+
+                fn null_column() -> ColumnData<'static> {
+                    #null_column
+                }
+
+                fn from_column(data: &ColumnData<'static>) -> tiberius::Result<Option<Self>> {
+                    #from_column
+                }
+            }
+
+            impl ToSql for #enum_ident {
+                fn to_sql(&self) -> ColumnData<'_> {
+                    ssql::SsqlEnum::to_column(self)
+                }
+            }
+
+            impl<'a> IntoSql<'a> for #enum_ident {
+                fn into_sql(self) -> ColumnData<'a> {
+                    ssql::SsqlEnum::to_column(&self)
+                }
+            }
+
+            impl<'a> tiberius::FromSql<'a> for #enum_ident {
+                fn from_sql(value: &'a ColumnData<'static>) -> tiberius::Result<Option<Self>> {
+                    <Self as ssql::SsqlEnum>::from_column(value)
+                }
+            }
+        }
+        .into()
+    }
+}
+
+fn column_variant(repr: &Ident) -> TokenStream {
+    match repr.to_string().as_str() {
+        "u8" => quote! { U8 },
+        "i16" => quote! { I16 },
+        "i32" => quote! { I32 },
+        _ => quote! { I64 },
+    }
+}
+
+/// Value of `#[ssql(name = "...")]` along with its tokens for error reporting,
+/// `name` being the only attribute allowed at this place.
+fn parse_str_attr(attrs: &[syn::Attribute], name: &str) -> syn::Result<Option<(String, Expr)>> {
+    let mut ret = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("ssql")) {
+        for meta in attr.parse_args_with(Punctuated::<Meta, Comma>::parse_terminated)? {
+            match meta {
+                Meta::NameValue(named_v) if named_v.path.is_ident(name) => {
+                    let value = match &named_v.value {
+                        Expr::Lit(ExprLit {
+                            lit: Lit::Str(v), ..
+                        }) => v.value(),
+                        v => v.to_token_stream().to_string(),
+                    };
+                    ret = Some((value, named_v.value));
+                }
+                meta => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        format!("unknown ssql attribute, expected `{}`", name),
+                    ))
+                }
+            }
+        }
+    }
+    Ok(ret)
+}
@@ -0,0 +1,102 @@
+use quote::quote;
+use syn::Data::Struct;
+use syn::Fields::Named;
+use syn::{DataStruct, DeriveInput, FieldsNamed, Ident};
+
+use crate::derive_ssql::FieldType;
+use crate::utils::parse_field_attrs;
+
+pub struct DeriveColumnGroup<'a> {
+    struct_ident: &'a Ident,
+    fields_type: Vec<FieldType<'a>>,
+    skipped: Vec<&'a Ident>,
+}
+
+impl<'a> DeriveColumnGroup<'a> {
+    pub(crate) fn new(ast: &'a DeriveInput) -> syn::Result<Self> {
+        let fields = match &ast.data {
+            Struct(DataStruct {
+                fields: Named(FieldsNamed { ref named, .. }),
+                ..
+            }) => named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &ast.ident,
+                    "ColumnGroup can only be derived for structs with named fields",
+                ))
+            }
+        };
+        for field in fields.iter() {
+            let attrs = parse_field_attrs(field)?;
+            if attrs.primary_key
+                || attrs.version
+                || attrs.readonly
+                || attrs.flatten
+                || attrs.foreign_key.is_some()
+            {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "fields of a ColumnGroup only support `rename`, `skip`, `with` and `json`",
+                ));
+            }
+        }
+        let skipped = fields
+            .iter()
+            .filter(|f| parse_field_attrs(f).unwrap_or_default().skip)
+            .map(|f| f.ident.as_ref().unwrap())
+            .collect();
+        // columns are looked up with the prefix given at runtime, thus no table name
+        let fields_type = fields
+            .iter()
+            .filter(|f| !parse_field_attrs(f).unwrap_or_default().skip)
+            .map(|f| FieldType::new(f, "", false, false))
+            .collect();
+        Ok(Self {
+            struct_ident: &ast.ident,
+            fields_type,
+            skipped,
+        })
+    }
+
+    pub(crate) fn expand(self) -> proc_macro::TokenStream {
+        let struct_ident = self.struct_ident;
+        let columns = self.fields_type.iter().map(|f| &f.column);
+        let values = self.fields_type.iter().map(|f| f.push_value());
+        let owned_values = self.fields_type.iter().map(|f| f.push_owned_value());
+        let from_row = self.fields_type.iter().map(|f| {
+            let ident = f.ident;
+            let column = &f.column;
+            let value = f.value_from_row(quote! { table }, quote! { format!("{}{}", prefix, #column).as_str() });
+            quote! { #ident: #value }
+        });
+        let skipped = &self.skipped;
+
+        quote! {
+            impl ssql::ColumnGroup for #struct_ident {
+                fn columns() -> Vec<&'static str> {
+                    vec![#(#columns,)*]
+                }
+
+                fn column_values(&self) -> SsqlResult<Vec<ColumnData<'_>>> {
+                    let mut values = vec![];
+                    #(#values)*
+                    Ok(values)
+                }
+
+                fn into_column_values(self) -> SsqlResult<Vec<ColumnData<'static>>> {
+                    let mut values = vec![];
+                    #(#owned_values)*
+                    Ok(values)
+                }
+
+                fn from_row(row: &Row, table: &str, prefix: &str) -> SsqlResult<Self> {
+                    Ok(Self {
+                        #(#from_row,)*
+                        #(#skipped: Default::default(),)*
+                    })
+                }
+            }
+        }
+        .into()
+    }
+}
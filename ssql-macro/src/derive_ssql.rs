@@ -1,103 +1,317 @@
 use proc_macro2::TokenStream;
-use quote::{quote, ToTokens};
-use syn::punctuated::Punctuated;
-use syn::token::Comma;
+use quote::{format_ident, quote, ToTokens};
 use syn::Data::Struct;
 use syn::Fields::Named;
-use syn::{DataStruct, DeriveInput, Field, FieldsNamed, Ident, parse_quote, Type};
+use syn::{DataStruct, DeriveInput, Field, FieldsNamed, Ident, parse_quote, Path, Type, Visibility};
 
-use crate::utils::{extract_type_from_option, get_relations_and_tables_and_pk, parse_table_name};
+use crate::utils::{
+    column_name, extract_type_from_option, get_relations_and_tables_and_pk, get_version_field,
+    parse_field_attrs, parse_table_name, quote_ident,
+};
 
 pub struct DeriveSsql<'a> {
     table_name: String,
     struct_ident: &'a Ident,
-    fields: &'a Punctuated<Field, Comma>,
+    vis: &'a Visibility,
     relations: Vec<String>,
     tables: Vec<String>,
-    primary_key: Option<Field>,
 
+    // fields mapped to a column, fields with `#[ssql(skip)]` are kept apart
     fields_type: Vec<FieldType<'a>>,
+    skipped: Vec<&'a Ident>,
 
     impl_fns: TokenStream,
+    // implementation of `SsqlKeyed`, only emitted if there is a primary key
+    keyed_fns: TokenStream,
+    // typed column constants, in an inherent impl
+    columns: TokenStream,
 }
 
-struct FieldType<'a> {
-    ident: &'a Ident,
+pub(crate) struct FieldType<'a> {
+    pub(crate) ident: &'a Ident,
+    // column name in database
+    pub(crate) column: String,
+    // declared type of the field
+    ty: &'a Type,
     // used for query
     query_name: String,
     //type for query in row.get::<?,?>()
     query_type: Type,
     #[cfg(feature = "polars")]
     origin_type: Type,
-    unwrap_quote: TokenStream,
+    // declared type without `Option`, named in decoding errors
+    type_name: String,
+    nullable: bool,
     to_string_quote: TokenStream,
+    primary_key: bool,
+    version: bool,
+    // selected but never inserted or updated
+    readonly: bool,
+    // module converting the field from and to sql values
+    with: Option<Path>,
+    // stored as JSON document, output as parsed document in json results
+    #[cfg(feature = "serde")]
+    json: bool,
+    // type and column prefix of a flattened column group
+    group: Option<(&'a Type, String)>,
+}
+
+impl<'a> FieldType<'a> {
+    pub(crate) fn new(field: &'a Field, table_name: &str, primary_key: bool, version: bool) -> Self {
+        let attrs = parse_field_attrs(field).unwrap_or_default();
+        let mut ty = &field.ty;
+        let nullable = match extract_type_from_option(&field.ty) {
+            None => false,
+            Some(v) => {
+                ty = v;
+                true
+            }
+        };
+        let type_name = ty.to_token_stream().to_string().replace(' ', "");
+        let (new_ty, to_string_quote) = match ty.to_token_stream().to_string().as_str() {
+            "String" => (parse_quote!(&str), quote! {.map(|i| i.to_string())}),
+            "Vec < u8 >" => (parse_quote!(&[u8]), quote! {.map(|i| i.to_vec())}),
+            _ => (ty.clone(), quote! {}),
+        };
+
+        let ident = field.ident.as_ref().unwrap();
+        let column = column_name(field);
+        let query_name = match table_name.is_empty() {
+            true => column.clone(),
+            false => {
+                format!("{}.{}", table_name, &column)
+            }
+        };
+        let group = match attrs.flatten {
+            true => Some((
+                &field.ty,
+                attrs.prefix.unwrap_or_else(|| format!("{}_", ident)),
+            )),
+            false => None,
+        };
+
+        FieldType {
+            ident,
+            column,
+            ty: &field.ty,
+            query_name,
+            query_type: new_ty,
+            #[cfg(feature = "polars")]
+            origin_type: ty.clone(),
+            type_name,
+            nullable,
+            to_string_quote,
+            primary_key,
+            version,
+            readonly: attrs.readonly,
+            with: attrs.with,
+            #[cfg(feature = "serde")]
+            json: attrs.json,
+            group,
+        }
+    }
+
+    fn insertable(&self) -> bool {
+        !self.version && !self.readonly
+    }
+
+    fn updatable(&self) -> bool {
+        !self.primary_key && !self.version && !self.readonly
+    }
+
+    // value of a field with custom conversion, JSON documents returning early if they fail to serialize
+    fn converted_value(&self, with: &Path) -> TokenStream {
+        let ident = self.ident;
+        #[cfg(feature = "serde")]
+        if self.json {
+            return quote! { #with::try_to_sql(&self.#ident)? };
+        }
+        quote! { #with::to_sql(&self.#ident) }
+    }
+
+    // converted value of a field with custom conversion or nullable, bound before being passed as parameter
+    fn param_binding(&self) -> TokenStream {
+        let ident = self.ident;
+        let param = format_ident!("__ssql_{}", ident);
+        match (&self.group, &self.with) {
+            (Some((ty, _)), _) => quote! {
+                let #param: Vec<ssql::utils::SqlValue> = <#ty as ssql::ColumnGroup>::column_values(&self.#ident)?
+                    .into_iter()
+                    .map(ssql::utils::SqlValue)
+                    .collect();
+            },
+            (None, Some(with)) => {
+                let value = self.converted_value(with);
+                quote! { let #param = ssql::utils::SqlValue(#value); }
+            }
+            (None, None) if self.nullable => {
+                quote! { let #param = ssql::utils::SqlValue(ssql::FieldValue::field_value(&self.#ident)); }
+            }
+            (None, None) => quote! {},
+        }
+    }
+
+    // statements appending quoted column names to `columns` and parameters to `params`
+    fn push_column_param(&self) -> TokenStream {
+        let ident = self.ident;
+        let param = format_ident!("__ssql_{}", ident);
+        match (&self.group, &self.with) {
+            (Some((ty, prefix)), _) => quote! {
+                columns.extend(
+                    <#ty as ssql::ColumnGroup>::columns()
+                        .into_iter()
+                        .map(|c| ssql::utils::quote_ident(&format!("{}{}", #prefix, c))),
+                );
+                params.extend(#param.iter().map(|v| v as &dyn ToSql));
+            },
+            (None, _) if self.with.is_some() || self.nullable => {
+                let column = quote_ident(&self.column);
+                quote! {
+                    columns.push(#column.to_string());
+                    params.push(&#param);
+                }
+            }
+            (None, _) => {
+                let column = quote_ident(&self.column);
+                quote! {
+                    columns.push(#column.to_string());
+                    params.push(&self.#ident);
+                }
+            }
+        }
+    }
+
+    // statement appending the sql values of this field to `values`
+    pub(crate) fn push_value(&self) -> TokenStream {
+        let ident = self.ident;
+        match (&self.group, &self.with) {
+            (Some((ty, _)), _) => {
+                quote! { values.extend(<#ty as ssql::ColumnGroup>::column_values(&self.#ident)?); }
+            }
+            (None, Some(with)) => {
+                let value = self.converted_value(with);
+                quote! { values.push(#value); }
+            }
+            // nullable enums do not implement `ToSql`
+            (None, None) if self.nullable => {
+                quote! { values.push(ssql::FieldValue::field_value(&self.#ident)); }
+            }
+            (None, None) => quote! { values.push(ToSql::to_sql(&self.#ident)); },
+        }
+    }
+
+    // same as `push_value`, consuming the field
+    pub(crate) fn push_owned_value(&self) -> TokenStream {
+        let ident = self.ident;
+        match (&self.group, &self.with) {
+            (Some((ty, _)), _) => {
+                quote! { values.extend(<#ty as ssql::ColumnGroup>::into_column_values(self.#ident)?); }
+            }
+            (None, Some(with)) => {
+                let value = self.converted_value(with);
+                quote! { values.push(#value); }
+            }
+            (None, None) if self.nullable => {
+                quote! { values.push(ssql::FieldValue::into_field_value(self.#ident)); }
+            }
+            (None, None) => quote! { values.push(self.#ident.into_sql()); },
+        }
+    }
+
+    // value of a non flattened field read from the column `name` of `row`,
+    // returning early with an error, `table` being empty for raw structs
+    pub(crate) fn value_from_row(&self, table: TokenStream, name: TokenStream) -> TokenStream {
+        let ty = &self.query_type;
+        let type_name = &self.type_name;
+        let to_string = &self.to_string_quote;
+        if let Some(with) = &self.with {
+            return quote! {
+                ssql::utils::decode_with(row, #table, #name, #with::from_sql)?
+            };
+        }
+        let value = quote! {
+            ssql::utils::decode::<#ty>(row, #table, #name, #type_name)?#to_string
+        };
+        match self.nullable {
+            true => value,
+            false => quote! {
+                ssql::utils::require(#value, row, #table, #name, #type_name)?
+            },
+        }
+    }
+
+    // prefix of flattened columns when read from a row
+    fn query_prefix(&self, table_name: &str) -> String {
+        let prefix = &self.group.as_ref().unwrap().1;
+        match table_name.is_empty() {
+            true => prefix.clone(),
+            false => format!("{}.{}", table_name, prefix),
+        }
+    }
 }
 
 impl<'a> DeriveSsql<'a> {
-    pub(crate) fn new(ast: &'a DeriveInput) -> Self {
-        let table_name = parse_table_name(&ast.attrs);
+    pub(crate) fn new(ast: &'a DeriveInput) -> syn::Result<Self> {
+        let table_name = parse_table_name(&ast.attrs)?;
         let fields = match &ast.data {
             Struct(DataStruct {
                        fields: Named(FieldsNamed { ref named, .. }),
                        ..
                    }) => named,
-            _ => unimplemented!(),
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &ast.ident,
+                    "ORM can only be derived for structs with named fields",
+                ))
+            }
         };
-        let (relations, tables, primary_key) =
-            get_relations_and_tables_and_pk(&table_name, &fields);
-
-        // let str: Type = parse_quote!(String);
-
-        let fields_type = fields.iter().map(|x| {
-            let mut ty = &x.ty;
-            let unwrap_quote = match extract_type_from_option(&x.ty) {
-                None => {
-                    quote! {
-                        .unwrap()
-                    }
-                }
-                Some(v) => {
-                    ty = v;
-                    quote! {}
+        let mut version_fields = 0;
+        for field in fields.iter() {
+            if parse_field_attrs(field)?.version {
+                version_fields += 1;
+                if version_fields > 1 {
+                    return Err(syn::Error::new_spanned(
+                        field,
+                        "only one field can be marked as version",
+                    ));
                 }
-            };
-            let (new_ty, to_string_quote) = if ty.to_token_stream().to_string().as_str() == "String" {
-                (parse_quote!(&str), quote! {.map(|i| i.to_string())})
-            } else {
-                (ty.clone(), quote! {})
-            };
+            }
+        }
+        let (relations, tables, primary_keys) =
+            get_relations_and_tables_and_pk(&table_name, &fields);
+        let version = get_version_field(fields);
 
-            let ident = x.ident.as_ref().unwrap();
-            let query_name = match table_name.is_empty() {
-                true => {
-                    format!("{}", &ident)
-                }
-                false => {
-                    format!("{}.{}", &table_name, &ident)
-                }
-            };
+        let skipped = fields
+            .iter()
+            .filter(|f| parse_field_attrs(f).unwrap_or_default().skip)
+            .map(|f| f.ident.as_ref().unwrap())
+            .collect();
 
-            FieldType {
-                ident,
-                query_name,
-                query_type: new_ty,
-                #[cfg(feature = "polars")]
-                origin_type: ty.clone(),
-                unwrap_quote: unwrap_quote,
-                to_string_quote: to_string_quote,
-            }
-        }).collect();
-        Self {
+        let fields_type = fields
+            .iter()
+            .filter(|f| !parse_field_attrs(f).unwrap_or_default().skip)
+            .map(|x| {
+                FieldType::new(
+                    x,
+                    &table_name,
+                    primary_keys.contains(x),
+                    version.as_ref() == Some(x),
+                )
+            })
+            .collect();
+        Ok(Self {
             table_name,
             struct_ident: &ast.ident,
-            fields,
+            vis: &ast.vis,
             relations,
             tables,
-            primary_key,
 
             fields_type: fields_type,
+            skipped,
             impl_fns: Default::default(),
-        }
+            keyed_fns: Default::default(),
+            columns: Default::default(),
+        })
     }
 
     pub(crate) fn impl_table_name(&mut self) {
@@ -112,14 +326,45 @@ impl<'a> DeriveSsql<'a> {
     }
 
     pub(crate) fn impl_fields(&mut self) {
-        let builder_fields_mapping = self
-            .fields
+        let builder_fields_mapping = self.fields_type.iter().map(|f| match &f.group {
+            Some((ty, prefix)) => {
+                quote! { fields.extend(<#ty as ssql::ColumnGroup>::prefixed_columns(#prefix)) }
+            }
+            None => {
+                let column = &f.column;
+                quote! { fields.push(#column) }
+            }
+        });
+        let builder_fields = match self.fields_type.iter().any(|f| f.group.is_some()) {
+            // names of flattened columns are only known at runtime
+            true => quote! {
+                static FIELDS: std::sync::OnceLock<Vec<&'static str>> = std::sync::OnceLock::new();
+                FIELDS
+                    .get_or_init(|| {
+                        let mut fields = vec![];
+                        #(#builder_fields_mapping;)*
+                        fields
+                    })
+                    .clone()
+            },
+            false => {
+                let columns = self.fields_type.iter().map(|f| &f.column);
+                quote! { vec![#(#columns,)*] }
+            }
+        };
+        let builder_readonly_fields = self
+            .fields_type
             .iter()
-            .map(|f| f.clone().ident.unwrap().to_string());
+            .filter(|f| f.readonly)
+            .map(|f| &f.column);
         self.impl_fns.extend(quote! {
 
             fn fields() -> Vec<&'static str> {
-                vec![#(#builder_fields_mapping,)*]
+                #builder_fields
+            }
+
+            fn readonly_fields() -> Vec<&'static str> {
+                vec![#(#builder_readonly_fields,)*]
             }
 
         });
@@ -142,29 +387,27 @@ impl<'a> DeriveSsql<'a> {
         })
     }
 
-    pub(crate) fn impl_insert_many(&mut self) {
-        let Self {
-            fields, table_name, ..
-        } = self;
-        let builder_insert_rows = fields.iter().map(|f| {
-            let field = f.clone().ident.unwrap();
-            return quote! {
-                row.push(item.#field.into_sql())
-            };
-        });
+    pub(crate) fn impl_field_values(&mut self) {
+        let builder_field_values = self.fields_type.iter().map(|f| f.push_value());
         self.impl_fns.extend(quote! {
 
-            async fn insert_many<I: IntoIterator<Item=Self> + Send>(iter: I, conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<u64>
-                where I::IntoIter: Send
-            {
-                let mut req = conn.bulk_insert(#table_name).await?;
-                for item in iter{
-                    let mut row = TokenRow::new();
-                    #(#builder_insert_rows;)*
-                    req.send(row).await?;
-                }
-                let res = req.finalize().await?;
-                Ok(res.total())
+            fn field_values(&self) -> SsqlResult<Vec<ColumnData<'_>>> {
+                let mut values = vec![];
+                #(#builder_field_values)*
+                Ok(values)
+            }
+
+        })
+    }
+
+    pub(crate) fn impl_into_columns_data(&mut self) {
+        let builder_columns_data = self.fields_type.iter().map(|f| f.push_owned_value());
+        self.impl_fns.extend(quote! {
+
+            fn into_columns_data(self) -> SsqlResult<Vec<ColumnData<'static>>> {
+                let mut values = vec![];
+                #(#builder_columns_data)*
+                Ok(values)
             }
 
         })
@@ -172,31 +415,28 @@ impl<'a> DeriveSsql<'a> {
 
     pub(crate) fn impl_insert(&mut self) {
         let Self {
-            fields, table_name, ..
+            fields_type,
+            table_name,
+            ..
         } = self;
-        let builder_insert_fields = fields
-            .iter()
-            .map(|f| f.clone().ident.unwrap().to_string())
-            .reduce(|cur: String, next: String| format!("{},{}", cur, &next))
-            .unwrap();
-        let mut fields_count = 0;
-        let builder_insert_params = fields
+        let builder_insert_bindings = fields_type
             .iter()
-            .map(|_| {
-                fields_count += 1;
-                return format!("@p{}", fields_count);
-            })
-            .reduce(|cur: String, next: String| format!("{},{}", cur, &next))
-            .unwrap();
-        let builder_insert_data = fields
+            .filter(|f| f.insertable())
+            .map(|f| f.param_binding());
+        let builder_insert_data = fields_type
             .iter()
-            .map(|f| f.ident.as_ref().unwrap())
-            .map(|f| quote! {&self.#f});
+            .filter(|f| f.insertable())
+            .map(|f| f.push_column_param());
         self.impl_fns.extend(quote! {
 
-             async fn insert(self, conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<()> {
-                let sql = format!("INSERT INTO {} ({}) values({})", #table_name, #builder_insert_fields, #builder_insert_params);
-                conn.execute(sql, &[#(#builder_insert_data,)*]).await?;
+             async fn insert_in(self, scope: &ssql::TableScope, conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<()> {
+                #(#builder_insert_bindings)*
+                let mut columns: Vec<String> = vec![];
+                let mut params: Vec<&dyn ToSql> = vec![];
+                #(#builder_insert_data)*
+                let placeholders: Vec<String> = (1..=params.len()).map(|i| format!("@p{}", i)).collect();
+                let sql = format!("INSERT INTO {} ({}) values({})", scope.resolve(#table_name), columns.join(","), placeholders.join(","));
+                conn.execute(sql.as_str(), params.as_slice()).await.map_err(|e| ssql::SsqlError::with_sql(e, &sql))?;
                 Ok(())
             }
 
@@ -206,36 +446,37 @@ impl<'a> DeriveSsql<'a> {
     pub(crate) fn impl_insert_ignore_pk(&mut self) {
         let Self {
             table_name,
-            fields,
-            primary_key,
+            fields_type,
             ..
         } = self;
-        let builder_insert_fields_ignore_pk = fields
-            .iter()
-            .filter(|f| Some(*f) != primary_key.as_ref())
-            .map(|f| f.clone().ident.unwrap().to_string())
-            .reduce(|cur: String, next: String| format!("{},{}", cur, &next))
-            .unwrap();
-        let mut fields_count = 0;
-        let builder_insert_params_ignore_pk = fields
+        let builder_insert_bindings_ignore_pk = fields_type
             .iter()
-            .filter(|f| Some(*f) != primary_key.as_ref())
-            .map(|_| {
-                fields_count += 1;
-                return format!("@p{}", fields_count);
-            })
-            .reduce(|cur: String, next: String| format!("{},{}", cur, &next))
-            .unwrap();
-        let builder_insert_data_ignore_pk = fields
+            .filter(|f| f.insertable() && !f.primary_key)
+            .map(|f| f.param_binding());
+        let builder_insert_data_ignore_pk = fields_type
             .iter()
-            .filter(|f| Some(*f) != primary_key.as_ref())
-            .map(|f| f.clone().ident.unwrap())
-            .map(|f| quote! {&self.#f});
+            .filter(|f| f.insertable() && !f.primary_key)
+            .map(|f| f.push_column_param());
         self.impl_fns.extend(quote! {
 
-            async fn insert_ignore_pk(self, conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<()> {
-                let sql = format!("INSERT INTO {} ({}) values({})", #table_name, #builder_insert_fields_ignore_pk, #builder_insert_params_ignore_pk);
-                conn.execute(sql, &[#(#builder_insert_data_ignore_pk,)*]).await?;
+            async fn insert_ignore_pk_in(self, scope: &ssql::TableScope, conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<()> {
+                #(#builder_insert_bindings_ignore_pk)*
+                let mut columns: Vec<String> = vec![];
+                let mut params: Vec<&dyn ToSql> = vec![];
+                #(#builder_insert_data_ignore_pk)*
+                let sql = match columns.is_empty() {
+                    true => format!("INSERT INTO {} DEFAULT VALUES", scope.resolve(#table_name)),
+                    false => {
+                        let placeholders: Vec<String> = (1..=params.len()).map(|i| format!("@p{}", i)).collect();
+                        format!(
+                            "INSERT INTO {} ({}) values({})",
+                            scope.resolve(#table_name),
+                            columns.join(","),
+                            placeholders.join(",")
+                        )
+                    }
+                };
+                conn.execute(sql.as_str(), params.as_slice()).await.map_err(|e| ssql::SsqlError::with_sql(e, &sql))?;
                 Ok(())
             }
 
@@ -244,14 +485,19 @@ impl<'a> DeriveSsql<'a> {
 
     pub(crate) fn impl_delete(&mut self) {
         let table_name = &self.table_name;
-        self.impl_fns.extend(quote! {
-            async fn delete(self, conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<()> {
-                let (pk, dt) = self.primary_key();
-                conn.execute(
-                    format!("DELETE FROM {} WHERE {} = @p1", #table_name, pk),
-                    &[dt],
-                )
-                .await?;
+        self.keyed_fns.extend(quote! {
+            async fn delete_in(self, scope: &ssql::TableScope, conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<()> {
+                let mut params: Vec<&dyn ToSql> = vec![];
+                let mut where_clause = vec![];
+                for (pk, dt) in self.primary_key().into_iter().chain(self.version()) {
+                    params.push(dt);
+                    where_clause.push(format!("{} = @p{}", ssql::utils::quote_ident(pk), params.len()));
+                }
+                let sql = format!("DELETE FROM {} WHERE {}", scope.resolve(#table_name), where_clause.join(" AND "));
+                let ret = conn.execute(sql.as_str(), params.as_slice()).await.map_err(|e| ssql::SsqlError::with_sql(e, &sql))?;
+                if ret.total() == 0 && self.version().is_some() {
+                    return Err(ssql::SsqlError::ConcurrencyConflict(#table_name.to_string()));
+                }
                 Ok(())
             }
         })
@@ -259,37 +505,49 @@ impl<'a> DeriveSsql<'a> {
 
     pub(crate) fn impl_update(&mut self) {
         let Self {
-            fields,
-            primary_key,
+            fields_type,
             table_name,
             ..
         } = self;
-        let mut fields_count = 0;
-        let builder_update_fields = fields
+        let builder_update_bindings = fields_type
             .iter()
-            .filter(|f| Some(*f) != primary_key.as_ref())
-            .map(|f| {
-                fields_count += 1;
-                return format!(
-                    " {} = @p{}",
-                    f.clone().ident.unwrap().to_string(),
-                    fields_count
-                );
-            })
-            .reduce(|cur: String, next: String| format!("{},{}", cur, &next))
-            .unwrap();
-        let builder_insert_data_ignore_pk = fields
+            .filter(|f| f.updatable())
+            .map(|f| f.param_binding());
+        let builder_update_data = fields_type
             .iter()
-            .filter(|f| Some(*f) != primary_key.as_ref())
-            .map(|f| f.clone().ident.unwrap())
-            .map(|f| quote! {&self.#f});
-        let builder_update_data = builder_insert_data_ignore_pk.clone();
-        self.impl_fns.extend(quote! {
-
-            async fn update(&self, conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<()> {
-                let (pk, dt) = self.primary_key();
-                let sql = format!("UPDATE {} SET {} WHERE {} =@p{}", #table_name, #builder_update_fields, pk, #fields_count + 1);
-                conn.execute(sql, &[#(#builder_update_data,)* dt]).await?;
+            .filter(|f| f.updatable())
+            .map(|f| f.push_column_param());
+        if !fields_type.iter().any(|f| f.updatable()) {
+            // no column apart from the primary key can be updated
+            self.keyed_fns.extend(quote! {
+                async fn update_in(&self, _scope: &ssql::TableScope, _conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<()> {
+                    Ok(())
+                }
+            });
+            return;
+        }
+        self.keyed_fns.extend(quote! {
+
+            async fn update_in(&self, scope: &ssql::TableScope, conn: &mut Client<Compat<TcpStream>>) -> SsqlResult<()> {
+                #(#builder_update_bindings)*
+                let mut columns: Vec<String> = vec![];
+                let mut params: Vec<&dyn ToSql> = vec![];
+                #(#builder_update_data)*
+                let set_clause: Vec<String> = columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| format!(" {} = @p{}", c, i + 1))
+                    .collect();
+                let mut where_clause = vec![];
+                for (pk, dt) in self.primary_key().into_iter().chain(self.version()) {
+                    params.push(dt);
+                    where_clause.push(format!("{} = @p{}", ssql::utils::quote_ident(pk), params.len()));
+                }
+                let sql = format!("UPDATE {} SET {} WHERE {}", scope.resolve(#table_name), set_clause.join(","), where_clause.join(" AND "));
+                let ret = conn.execute(sql.as_str(), params.as_slice()).await.map_err(|e| ssql::SsqlError::with_sql(e, &sql))?;
+                if ret.total() == 0 && self.version().is_some() {
+                    return Err(ssql::SsqlError::ConcurrencyConflict(#table_name.to_string()));
+                }
                 Ok(())
             }
 
@@ -297,41 +555,71 @@ impl<'a> DeriveSsql<'a> {
     }
 
     pub(crate) fn impl_primary_key(&mut self) {
-        let primary_key = &self.primary_key;
-        let pk = if let Some(f) = &primary_key {
-            let field_name = f.ident.as_ref().unwrap().to_string();
-            let mn = f.ident.as_ref().unwrap();
-            quote! {
-                fn primary_key(&self) -> (&'static str, &dyn ToSql) {
-                    (#field_name, &self.#mn)
-                }
+        let primary_keys: Vec<_> = self.fields_type.iter().filter(|f| f.primary_key).collect();
+        let field_names: Vec<_> = primary_keys.iter().map(|f| &f.column).collect();
+        let mn = primary_keys.iter().map(|f| f.ident);
+        self.keyed_fns.extend(quote! {
+            fn primary_key_fields() -> Vec<&'static str> {
+                vec![#(#field_names,)*]
             }
-        } else {
-            quote! {
-                fn primary_key(&self) -> (&'static str, &dyn ToSql) {
-                    unimplemented!("Primary key not set");
+
+            fn primary_key(&self) -> Vec<(&'static str, &dyn ToSql)> {
+                vec![#((#field_names, &self.#mn as &dyn ToSql),)*]
+            }
+        });
+    }
+
+    pub(crate) fn impl_version(&mut self) {
+        let version = match self.fields_type.iter().find(|f| f.version) {
+            Some(f) => {
+                let field_name = &f.column;
+                let mn = f.ident;
+                quote! {
+                    fn version(&self) -> Option<(&'static str, &dyn ToSql)> {
+                        Some((#field_name, &self.#mn))
+                    }
+
+                    fn set_version(&mut self, version: Vec<u8>) {
+                        self.#mn = version.into();
+                    }
                 }
             }
+            None => quote! {
+                fn version(&self) -> Option<(&'static str, &dyn ToSql)> {
+                    None
+                }
+
+                fn set_version(&mut self, _version: Vec<u8>) {}
+            },
         };
-        self.impl_fns.extend(pk);
+        self.impl_fns.extend(version);
     }
 
     pub(crate) fn impl_relationship(&mut self) {
-        let builder_fields = self
-            .relations
-            .iter()
-            .zip(self.tables.iter())
-            .map(|(rel, tb)| {
-                quote! { #tb => {
-                    concat!(" ", #tb, " ON ", #rel)
-                }}
-            });
+        // relations pointing to the same table form a composite foreign key
+        let mut grouped: Vec<(&String, Vec<&String>)> = vec![];
+        for (rel, tb) in self.relations.iter().zip(self.tables.iter()) {
+            match grouped.iter_mut().find(|(t, _)| *t == tb) {
+                Some((_, rels)) => rels.push(rel),
+                None => grouped.push((tb, vec![rel])),
+            }
+        }
+        let builder_fields = grouped.iter().map(|(tb, rels)| {
+            let on = rels
+                .iter()
+                .map(|r| r.as_str())
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            quote! { #tb => {
+                Some(#on)
+            }}
+        });
         self.impl_fns.extend(quote! {
 
-            fn relationship(input: &str) -> &'static str {
+            fn relationship(input: &str) -> Option<&'static str> {
                 match input {
                     #(#builder_fields,)*
-                    _ =>  unimplemented!("relationship not found"),
+                    _ => None,
                 }
             }
 
@@ -340,23 +628,28 @@ impl<'a> DeriveSsql<'a> {
 
     pub(crate) fn impl_row_to_struct(&mut self) {
         let fields_type = &self.fields_type;
+        let table_name = &self.table_name;
         let builder_row_to_self_func = fields_type.iter().map(|f| {
             let ident = &f.ident;
-            let ty = &f.query_type;
-            let unwrap = &f.unwrap_quote;
-            let to_string = &f.to_string_quote;
+            if let Some((ty, _)) = &f.group {
+                let prefix = f.query_prefix(table_name);
+                return quote! {
+                    #ident: <#ty as ssql::ColumnGroup>::from_row(row, #table_name, #prefix)?
+                };
+            }
             let query_name = &f.query_name;
-
-
-            return quote! {
-                #ident: row.get::<#ty, &str>(#query_name)#to_string #unwrap
-            };
+            let value = f.value_from_row(quote! { #table_name }, quote! { #query_name });
+            quote! {
+                #ident: #value
+            }
         });
+        let skipped = &self.skipped;
         self.impl_fns.extend(quote! {
-            fn row_to_struct(row:&Row) -> Self {
-                Self{
+            fn row_to_struct(row:&Row) -> SsqlResult<Self> {
+                Ok(Self{
                     #(#builder_row_to_self_func,)*
-                }
+                    #(#skipped: Default::default(),)*
+                })
             }
         });
     }
@@ -364,20 +657,40 @@ impl<'a> DeriveSsql<'a> {
     #[cfg(feature = "serde")]
     pub(crate) fn impl_row_to_json(&mut self) {
         let fields_type = &self.fields_type;
+        let table_name = &self.table_name;
         let builder_row_func = fields_type.iter().map(|f| {
             let ident_str = f.ident.to_string();
             let query_name = &f.query_name;
             let ty = &f.query_type;
+            let type_name = &f.type_name;
+            if let Some((group_ty, _)) = &f.group {
+                // nested object of the group
+                let prefix = f.query_prefix(&self.table_name);
+                return quote! {
+                    map.insert(#ident_str.to_string(), <#group_ty as ssql::ColumnGroup>::from_row(row, #table_name, #prefix)?.serialize(Serializer)?)
+                };
+            }
+            if f.json {
+                return quote! {
+                    map.insert(#ident_str.to_string(), ssql::json_column::value(ssql::utils::column_data(row, #query_name)))
+                };
+            }
+            if f.with.is_some() {
+                // stored value as text, the field type is not required to be serializable
+                return quote! {
+                    map.insert(#ident_str.to_string(), ssql::utils::column_data(row, #query_name).and_then(ssql::utils::column_text).serialize(Serializer)?)
+                };
+            }
             return quote! {
-                map.insert(#ident_str.to_string(), row.get::<#ty, &str>(#query_name).serialize(Serializer).unwrap())
+                map.insert(#ident_str.to_string(), ssql::utils::decode::<#ty>(row, #table_name, #query_name, #type_name)?.serialize(Serializer)?)
             };
         });
         self.impl_fns.extend(quote! {
 
-            fn row_to_json(row:&Row) -> Map<String, Value> {
+            fn row_to_json(row:&Row) -> SsqlResult<Map<String, Value>> {
                 let mut map = Map::new();
                 #(#builder_row_func;)*
-                map
+                Ok(map)
             }
 
         })
@@ -386,58 +699,140 @@ impl<'a> DeriveSsql<'a> {
     #[cfg(feature = "polars")]
     pub(crate) fn impl_dataframe(&mut self) {
         let fields_type = &self.fields_type;
+        let table_name = &self.table_name;
+        // locals are prefixed, so that fields named like a type in scope do not resolve to it
         let builder_new_vecs = fields_type.iter().map(|f| {
-            let ident = f.ident;
-            let ty = &f.origin_type;
+            let ident = format_ident!("__ssql_{}", f.ident);
+            if let Some((ty, _)) = &f.group {
+                // one vec per column of the group
+                return quote! {
+                    let mut #ident: Vec<Vec<Option<String>>> =
+                        <#ty as ssql::ColumnGroup>::columns().iter().map(|_| vec![]).collect()
+                };
+            }
+            let ty = match f.with {
+                Some(_) => parse_quote!(String),
+                None => f.origin_type.clone(),
+            };
             quote! {
                 let mut #ident : Vec<Option<#ty>> = vec![]
             }
         });
 
         let builder_insert_to_df = fields_type.iter().map(|f| {
-            let field = f.ident;
+            let field = format_ident!("__ssql_{}", f.ident);
             let ty = &f.query_type;
+            let type_name = &f.type_name;
             let query_name = &f.query_name;
             let to_string = &f.to_string_quote;
+            if let Some((group_ty, _)) = &f.group {
+                // flattened columns hold the text of the stored values
+                let prefix = f.query_prefix(&self.table_name);
+                return quote! {
+                    for (values, column) in #field.iter_mut().zip(<#group_ty as ssql::ColumnGroup>::columns()) {
+                        values.push(
+                            ssql::utils::column_data(row, &format!("{}{}", #prefix, column))
+                                .and_then(ssql::utils::column_text),
+                        );
+                    }
+                };
+            }
+            if f.with.is_some() {
+                return quote! {
+                    #field.push(ssql::utils::column_data(row, #query_name).and_then(ssql::utils::column_text))
+                };
+            }
             quote! {
-                #field.push(row.get::<#ty, &str>(#query_name)#to_string)
+                // NULL whatever the field type, as columns of the outer side of a join are
+                #field.push(ssql::utils::decode::<#ty>(row, #table_name, #query_name, #type_name)?#to_string)
             }
         });
 
-        let builder_df = fields_type.iter().map(|f| {
-            let field = f.ident;
-            let mn = field.to_string();
+        let builder_df = fields_type.iter().filter(|f| f.group.is_none()).map(|f| {
+            let field = format_ident!("__ssql_{}", f.ident);
+            let mn = f.ident.to_string();
+            let ty = match f.with {
+                Some(_) => parse_quote!(String),
+                None => f.origin_type.clone(),
+            };
             quote! {
-                #mn => #field
+                <#ty as ssql::ToSeries>::to_series(#mn, #field)?
             }
         });
 
+        // flattened columns are appended after the other columns
+        let builder_group_columns = fields_type.iter().filter_map(|f| {
+            let field = format_ident!("__ssql_{}", f.ident);
+            f.group.as_ref().map(|(ty, prefix)| {
+                quote! {
+                    for (values, column) in #field.into_iter().zip(<#ty as ssql::ColumnGroup>::columns()) {
+                        df.with_column(Series::new(&format!("{}{}", #prefix, column), values))?;
+                    }
+                }
+            })
+        });
+
         self.impl_fns.extend(quote! {
 
-            async fn dataframe<'a>(stream: QueryStream<'a>) -> SsqlResult<DataFrame> {
+            fn dataframe_from_rows(rows: &[Row]) -> SsqlResult<DataFrame> {
                 #(#builder_new_vecs;)*
-                #[allow(non_snake_case)]
-                let mut stream = stream.into_row_stream();
-                while let Some(row) = stream.try_next().await? {
+                for row in rows {
                     #(#builder_insert_to_df;)*
                 }
-                Ok(
-                    df!(
+                #[allow(unused_mut)]
+                let mut df = DataFrame::new(vec![
                     #(#builder_df,)*
-                )?
-                )
+                ])?;
+                #(#builder_group_columns)*
+                Ok(df)
             }
 
         });
     }
 
+    pub(crate) fn impl_columns(&mut self) {
+        let struct_name = self.struct_ident;
+        let vis = self.vis;
+        for field in self.fields_type.iter().filter(|f| f.group.is_none()) {
+            let name = format_ident!(
+                "{}",
+                field.ident.to_string().trim_start_matches("r#").to_uppercase()
+            );
+            let ty = field.ty;
+            let column = &field.column;
+            self.columns.extend(quote! {
+                #vis const #name: ssql::Column<#struct_name, #ty> = ssql::Column::new(#column);
+            });
+        }
+    }
+
     pub(crate) fn finalize(self) -> proc_macro::TokenStream {
         let struct_name = self.struct_ident;
         let fns = self.impl_fns;
+        // update and delete fail to compile on structs without primary key
+        let keyed = match self.fields_type.iter().any(|f| f.primary_key) {
+            true => {
+                let keyed_fns = self.keyed_fns;
+                quote! {
+                    impl SsqlKeyed for #struct_name {
+                        #keyed_fns
+                    }
+                }
+            }
+            false => quote! {},
+        };
+        let columns = self.columns;
         quote! {
             impl SsqlMarker for #struct_name {
                 #fns
             }
+
+            #[allow(dead_code)]
+            impl #struct_name {
+                #columns
+            }
+
+            #keyed
         }
         .into()
     }
@@ -1,26 +1,37 @@
 use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
 
+use derive_enum::DeriveSsqlEnum;
+use derive_group::DeriveColumnGroup;
 use derive_ssql::DeriveSsql;
 
+mod derive_enum;
+mod derive_group;
 mod derive_ssql;
 mod utils;
 
 #[proc_macro_derive(ORM, attributes(ssql))]
 pub fn ssql(tokens: TokenStream) -> TokenStream {
-    let ast: syn::DeriveInput = syn::parse(tokens).unwrap();
+    let ast = parse_macro_input!(tokens as DeriveInput);
 
-    let mut impls = DeriveSsql::new(&ast);
+    let mut impls = match DeriveSsql::new(&ast) {
+        Ok(impls) => impls,
+        Err(e) => return e.to_compile_error().into(),
+    };
     impls.impl_table_name();
     impls.impl_fields();
 
     impls.impl_query();
     impls.impl_primary_key();
+    impls.impl_version();
     impls.impl_insert();
     impls.impl_insert_ignore_pk();
-    impls.impl_insert_many();
+    impls.impl_field_values();
+    impls.impl_into_columns_data();
     impls.impl_update();
     impls.impl_delete();
     impls.impl_relationship();
+    impls.impl_columns();
 
     impls.impl_row_to_struct();
 
@@ -33,3 +44,19 @@ pub fn ssql(tokens: TokenStream) -> TokenStream {
     impls.finalize()
 }
 
+
+#[proc_macro_derive(SsqlEnum, attributes(ssql))]
+pub fn ssql_enum(tokens: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(tokens as DeriveInput);
+    DeriveSsqlEnum::new(&ast)
+        .map(DeriveSsqlEnum::expand)
+        .unwrap_or_else(|e| e.to_compile_error().into())
+}
+
+#[proc_macro_derive(ColumnGroup, attributes(ssql))]
+pub fn column_group(tokens: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(tokens as DeriveInput);
+    DeriveColumnGroup::new(&ast)
+        .map(DeriveColumnGroup::expand)
+        .unwrap_or_else(|e| e.to_compile_error().into())
+}
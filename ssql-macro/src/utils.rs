@@ -1,5 +1,5 @@
-use syn::{punctuated::Punctuated, token::Comma, Meta, Path};
-use syn::{Expr, ExprLit, Field, Lit};
+use syn::{parse_quote, punctuated::Punctuated, token::Comma, Meta, Path};
+use syn::{Expr, ExprLit, Field, Lit, LitStr};
 
 pub(crate) fn extract_type_from_option(ty: &syn::Type) -> Option<&syn::Type> {
     use syn::{GenericArgument, PathArguments, PathSegment};
@@ -45,128 +45,218 @@ pub(crate) fn extract_type_from_option(ty: &syn::Type) -> Option<&syn::Type> {
         })
 }
 
-pub(crate) fn parse_table_name(attrs: &Vec<syn::Attribute>) -> String {
-    let mut table: (String, String) = ("".to_string(), "".to_string());
-    for attr in attrs.iter() {
-        if let Some(ident) = attr.path().get_ident() {
-            if ident == "ssql" {
-                if let Ok(list) = attr.parse_args_with(Punctuated::<Meta, Comma>::parse_terminated)
-                {
-                    for meta in list.iter() {
-                        if let Meta::NameValue(named_v) = meta {
-                            let Path { ref segments, .. } = &named_v.path;
-                            for ssql_segs in segments.iter() {
-                                if ssql_segs.ident == "table" {
-                                    // let b = &named_v.value;
-                                    if let Expr::Path(p_v) = &named_v.value {
-                                        for seg in p_v.path.segments.iter() {
-                                            let i = &seg.ident;
-                                            table.0 = i.to_string();
-                                            // return i.to_string();
-                                        }
-                                    }
-                                } else if ssql_segs.ident == "schema" {
-                                    if let Expr::Path(p_v) = &named_v.value {
-                                        for seg in p_v.path.segments.iter() {
-                                            let i = &seg.ident;
-                                            table.1 = i.to_string();
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+pub(crate) fn parse_table_name(attrs: &[syn::Attribute]) -> syn::Result<String> {
+    let mut table = String::new();
+    let mut schema = String::new();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("ssql")) {
+        for meta in attr.parse_args_with(Punctuated::<Meta, Comma>::parse_terminated)? {
+            match &meta {
+                // raw query structs leave the table empty
+                Meta::Path(p) if p.is_ident("table") => {}
+                Meta::NameValue(named_v) if named_v.path.is_ident("table") => {
+                    table = parse_name(&named_v.value)?
+                }
+                Meta::NameValue(named_v) if named_v.path.is_ident("schema") => {
+                    schema = parse_name(&named_v.value)?
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        "unknown ssql attribute, expected `table` or `schema`",
+                    ))
                 }
             }
         }
     }
 
-    // match table_name, schema
-    match (table.0.as_str(), table.1.as_str()) {
-        // ("", _) => unimplemented!(),
-        (_, "") => table.0,
-        _ => format!("{}.{}", table.1, table.0),
+    match schema.is_empty() {
+        true => Ok(table),
+        false => Ok(format!("{}.{}", schema, table)),
+    }
+}
+
+/// Name given either as a path `Person` or a string literal `"Order Details"`.
+fn parse_name(value: &Expr) -> syn::Result<String> {
+    match value {
+        Expr::Path(p_v) if !p_v.path.segments.is_empty() => {
+            Ok(p_v.path.segments.last().unwrap().ident.to_string())
+        }
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(v), ..
+        }) => Ok(v.value()),
+        _ => Err(syn::Error::new_spanned(
+            value,
+            "expected a name or a string literal",
+        )),
+    }
+}
+
+fn parse_str(value: &Expr) -> syn::Result<&LitStr> {
+    match value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(v), ..
+        }) => Ok(v),
+        _ => Err(syn::Error::new_spanned(value, "expected a string literal")),
     }
 }
 
+/// Quote an identifier with brackets, escaping `]`, same as `ssql::utils::quote_ident`.
+pub(crate) fn quote_ident(name: &str) -> String {
+    format!("[{}]", name.replace(']', "]]"))
+}
+
+/// Join conditions of fields with `#[ssql(foreign_key = "...")]` along with the tables they refer to,
+/// and fields with `#[ssql(primary_key)]`.
 pub(crate) fn get_relations_and_tables_and_pk(
     table_name: &String,
     fields: &Punctuated<Field, Comma>,
-) -> (Vec<String>, Vec<String>, Option<Field>) {
+) -> (Vec<String>, Vec<String>, Vec<Field>) {
     let mut relations: Vec<String> = vec![];
     let mut tables: Vec<String> = vec![];
-    let mut primary_key = None;
+    let mut targets: Vec<String> = vec![];
+    let mut primary_keys = vec![];
     for field in fields.iter() {
-        for attr in field.attrs.iter() {
-            if let Some(ident) = attr.path().get_ident() {
-                if ident == "ssql" {
-                    if let Ok(list) =
-                        attr.parse_args_with(Punctuated::<Meta, Comma>::parse_terminated)
-                    {
-                        for meta in list.iter() {
-                            if let Meta::Path(path) = meta {
-                                let Path { ref segments, .. } = path;
-                                for ssql_tags in segments.iter() {
-                                    if ssql_tags.ident == "primary_key" {
-                                        primary_key = Some(field.clone());
-                                    }
-                                }
-                            }
+        let attrs = parse_field_attrs(field).unwrap_or_default();
+        if attrs.primary_key {
+            primary_keys.push(field.clone());
+        }
+        if let Some(foreign_key) = attrs.foreign_key {
+            // tables are referred to by their derived name as alias
+            let foreign_key = foreign_key.value();
+            // another relationship to the same column, joins use the first one
+            if targets.contains(&foreign_key) {
+                continue;
+            }
+            // checked to contain a dot when parsed
+            let (fk_table, fk_column) = foreign_key.rsplit_once('.').unwrap();
+            relations.push(format!(
+                "{}.{} = {}.{}",
+                quote_ident(table_name),
+                quote_ident(&column_name(field)),
+                quote_ident(fk_table),
+                quote_ident(fk_column)
+            ));
+            tables.push(fk_table.to_string());
+            targets.push(foreign_key);
+        }
+    }
+    (relations, tables, primary_keys)
+}
 
-                            if let Meta::NameValue(named_v) = meta {
-                                let Path { ref segments, .. } = &named_v.path;
-                                for ssql_tags in segments.iter() {
-                                    if ssql_tags.ident == "foreign_key" {
-                                        if let Expr::Lit(ExprLit { lit, .. }) = &named_v.value {
-                                            if let Lit::Str(v) = lit {
-                                                let field_name =
-                                                    field.ident.as_ref().unwrap().to_string();
-                                                relations.push(format!(
-                                                    "{}.{} = {}",
-                                                    &table_name,
-                                                    field_name,
-                                                    v.value()
-                                                ));
-                                                tables.push(
-                                                    v.value()[..v.value().rfind('.').unwrap()]
-                                                        .to_string(),
-                                                );
-                                            }
-                                        }
-                                        // if let Expr::Path(p_v) = &named_v.value {
-                                        //     dbg!(&p_v);
-                                        //     for seg in p_v.path.segments.iter() {
-                                        //         let i = &seg.ident;
-                                        //         result.extend(quote! {
-                                        //                 impl #struct_name {
-                                        //                     fn #i() -> String{
-                                        //                         "asdf".to_string()
-                                        //                     }
-                                        //                 }
-                                        //             })
-                                        //     }
-                                        // }
-                                        // if let Expr::Lit{, ..} = &named_v.value {
-                                        //     dbg!(&p_v);
-                                        //     for seg in p_v.path.segments.iter() {
-                                        //         let i = &seg.ident;
-                                        //         result.extend(quote! {
-                                        //                 impl #struct_name {
-                                        //                     fn #i() -> String{
-                                        //                         "asdf".to_string()
-                                        //                     }
-                                        //                 }
-                                        //             })
-                                        //     }
-                                        // }
-                                    }
-                                }
-                            }
+/// The field marked `#[ssql(version)]`, checked to be unique when validated.
+pub(crate) fn get_version_field(fields: &Punctuated<Field, Comma>) -> Option<Field> {
+    fields
+        .iter()
+        .find(|field| parse_field_attrs(field).unwrap_or_default().version)
+        .cloned()
+}
+
+/// Column related attributes of one field.
+#[derive(Default)]
+pub(crate) struct FieldAttrs {
+    /// `#[ssql(primary_key)]`, part of the primary key.
+    pub(crate) primary_key: bool,
+    /// `#[ssql(foreign_key = "table.column")]`, column this field refers to in another table.
+    pub(crate) foreign_key: Option<LitStr>,
+    /// `#[ssql(version)]`, rowversion column checked on update and delete.
+    pub(crate) version: bool,
+    /// `#[ssql(rename = "...")]`, column name if it differs from the field name.
+    pub(crate) rename: Option<String>,
+    /// `#[ssql(skip)]`, not persisted, filled with `Default` when loading.
+    pub(crate) skip: bool,
+    /// `#[ssql(readonly)]` or `#[ssql(computed)]`, selected but never inserted or updated.
+    pub(crate) readonly: bool,
+    /// `#[ssql(with = "module")]`, module providing `to_sql` and `from_sql` for the field type.
+    pub(crate) with: Option<Path>,
+    /// `#[ssql(json)]`, stored as JSON document, converted with `ssql::json_column`.
+    pub(crate) json: bool,
+    /// `#[ssql(flatten)]`, columns of a `ColumnGroup` stored in this table.
+    pub(crate) flatten: bool,
+    /// `#[ssql(prefix = "...")]`, prefix of flattened columns, the field name and `_` by default.
+    pub(crate) prefix: Option<String>,
+}
+
+pub(crate) fn parse_field_attrs(field: &Field) -> syn::Result<FieldAttrs> {
+    let mut attrs = FieldAttrs::default();
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("ssql")) {
+        for meta in attr.parse_args_with(Punctuated::<Meta, Comma>::parse_terminated)? {
+            match &meta {
+                Meta::Path(p) if p.is_ident("primary_key") => attrs.primary_key = true,
+                Meta::Path(p) if p.is_ident("version") => attrs.version = true,
+                Meta::Path(p) if p.is_ident("skip") => attrs.skip = true,
+                Meta::Path(p) if p.is_ident("readonly") || p.is_ident("computed") => {
+                    attrs.readonly = true
+                }
+                #[cfg(feature = "serde")]
+                Meta::Path(p) if p.is_ident("json") => attrs.json = true,
+                #[cfg(not(feature = "serde"))]
+                Meta::Path(p) if p.is_ident("json") => {
+                    return Err(syn::Error::new_spanned(
+                        p,
+                        "`json` fields require feature `serde` of ssql",
+                    ))
+                }
+                Meta::Path(p) if p.is_ident("flatten") => attrs.flatten = true,
+                Meta::NameValue(named_v) if named_v.path.is_ident("foreign_key") => {
+                    let v = parse_str(&named_v.value)?;
+                    match v.value().rsplit_once('.') {
+                        Some((table, column)) if !table.is_empty() && !column.is_empty() => {}
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                v,
+                                "foreign_key must be given as \"table.column\" or \"schema.table.column\"",
+                            ))
                         }
                     }
+                    attrs.foreign_key = Some(v.clone());
+                }
+                Meta::NameValue(named_v) if named_v.path.is_ident("rename") => {
+                    attrs.rename = Some(parse_str(&named_v.value)?.value());
+                }
+                Meta::NameValue(named_v) if named_v.path.is_ident("with") => {
+                    let v = parse_str(&named_v.value)?;
+                    attrs.with = Some(v.parse().map_err(|_| {
+                        syn::Error::new_spanned(v, "expected a module path like \"my_module\"")
+                    })?);
+                }
+                Meta::NameValue(named_v) if named_v.path.is_ident("prefix") => {
+                    attrs.prefix = Some(parse_str(&named_v.value)?.value());
                 }
+                _ => return Err(syn::Error::new_spanned(meta, "unknown ssql attribute")),
             }
         }
     }
-    (relations, tables, primary_key)
+
+    let conflict = |msg: &str| Err(syn::Error::new_spanned(field, msg));
+    if attrs.json {
+        if attrs.with.is_some() {
+            return conflict("`json` and `with` can not be used together");
+        }
+        attrs.with = Some(parse_quote!(ssql::json_column));
+    }
+    if attrs.flatten
+        && (attrs.primary_key
+            || attrs.version
+            || attrs.readonly
+            || attrs.foreign_key.is_some()
+            || attrs.rename.is_some()
+            || attrs.with.is_some())
+    {
+        return conflict("`flatten` can only be combined with `prefix`");
+    }
+    if attrs.prefix.is_some() && !attrs.flatten {
+        return conflict("`prefix` requires `flatten`");
+    }
+    if attrs.skip && (attrs.primary_key || attrs.version || attrs.foreign_key.is_some()) {
+        return conflict("skipped fields can not be primary key, version or foreign key");
+    }
+    Ok(attrs)
+}
+
+/// Name of the column a field maps to.
+pub(crate) fn column_name(field: &Field) -> String {
+    parse_field_attrs(field)
+        .unwrap_or_default()
+        .rename
+        .unwrap_or_else(|| field.ident.as_ref().unwrap().to_string())
 }
@@ -1,6 +1,7 @@
 #![allow(non_snake_case)]
 #![cfg(feature = "polars")]
 
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
 use tokio::net::TcpStream;
 use tokio_util::compat::Compat;
 
@@ -15,6 +16,107 @@ async fn data_frame() {
     dbg!(df);
 }
 
+#[tokio::test]
+async fn joined_data_frame() {
+    let mut client = get_client().await;
+    let query = Customerlist::query().left_join::<Test>();
+    let (customers, slow_moving) = query.df(&mut client).await.unwrap();
+    let rows = query.all(&mut client).await.unwrap();
+    assert_eq!(customers.height(), rows.len());
+    assert_eq!(slow_moving.height(), rows.len());
+    // customers without a match hold nulls on the joined side
+    let unmatched = rows.iter().filter(|(_, t)| t.stock_in_day.is_none()).count();
+    assert_eq!(slow_moving.column("stock_in_day").unwrap().null_count(), unmatched);
+}
+
+#[tokio::test]
+async fn data_frame_dtypes() {
+    let mut client = get_client().await;
+    let df = Test::query().df(&mut client).await.unwrap();
+    assert_eq!(
+        df.column("Generated_Time").unwrap().dtype(),
+        &DataType::Datetime(TimeUnit::Microseconds, None)
+    );
+    assert_eq!(df.column("total_value").unwrap().dtype(), &DataType::Float64);
+
+    // the second row holds a null of every type
+    let df = UntypedRawQuery::new(
+        "SELECT CAST('2024-02-29' AS date) AS d, CAST('13:45:30.5' AS time) AS t, \
+        CAST('2024-02-29 13:45:30 +02:00' AS datetimeoffset) AS dto, CAST(12.5 AS decimal(10, 3)) AS dec, \
+        CAST('6F9619FF-8B86-D011-B42D-00C04FC964FF' AS uniqueidentifier) AS guid, \
+        CAST(0x0102FF AS varbinary(10)) AS bin \
+        UNION ALL SELECT NULL, NULL, NULL, NULL, NULL, NULL",
+        &[],
+    )
+    .df(&mut client)
+    .await
+    .unwrap();
+    for name in ["d", "t", "dto", "dec", "guid", "bin"] {
+        assert_eq!(df.column(name).unwrap().null_count(), 1, "{}", name);
+    }
+
+    let d = df.column("d").unwrap();
+    assert_eq!(d.dtype(), &DataType::Date);
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+    let days = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap().signed_duration_since(epoch).num_days();
+    assert_eq!(d.date().unwrap().get(0), Some(days as i32));
+    assert_eq!(d.date().unwrap().get(1), None);
+
+    let t = df.column("t").unwrap();
+    assert_eq!(t.dtype(), &DataType::Time);
+    assert_eq!(t.time().unwrap().get(0), Some(49_530_500_000_000));
+
+    let dto = df.column("dto").unwrap();
+    assert_eq!(
+        dto.dtype(),
+        &DataType::Datetime(TimeUnit::Microseconds, Some("UTC".to_string()))
+    );
+    let utc = DateTime::parse_from_rfc3339("2024-02-29T13:45:30+02:00").unwrap().timestamp_micros();
+    assert_eq!(dto.datetime().unwrap().get(0), Some(utc));
+
+    // values are stored unscaled along with the scale of the column
+    let dec = df.column("dec").unwrap();
+    assert_eq!(dec.dtype(), &DataType::Decimal(None, Some(3)));
+    assert_eq!(dec.decimal().unwrap().get(0), Some(12_500));
+
+    let guid = df.column("guid").unwrap();
+    assert_eq!(guid.dtype(), &DataType::String);
+    assert_eq!(guid.str().unwrap().get(0), Some("6f9619ff-8b86-d011-b42d-00c04fc964ff"));
+
+    let bin = df.column("bin").unwrap();
+    assert_eq!(bin.dtype(), &DataType::Binary);
+    assert_eq!(bin.binary().unwrap().get(0), Some(&[1u8, 2, 255][..]));
+}
+
+#[tokio::test]
+async fn untyped_data_frame() {
+    let mut client = get_client().await;
+    let df = UntypedRawQuery::new("SELECT id, Email FROM Person WHERE id > @p1", &[&0])
+        .df(&mut client)
+        .await
+        .unwrap();
+    assert_eq!(df.get_column_names(), &["id", "Email"]);
+    assert_eq!(df.column("id").unwrap().dtype(), &DataType::Int32);
+    assert_eq!(df.column("Email").unwrap().dtype(), &DataType::String);
+}
+
+#[tokio::test]
+async fn data_frame_field_named_like_type() {
+    // `Person` has a field `Email`, named like the tuple struct below
+    let mut client = get_client().await;
+    let df = Person::query().df(&mut client).await.unwrap();
+    let emails: Vec<Email> = df
+        .column("Email")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .map(|e| Email(e.to_string()))
+        .collect();
+    assert_eq!(emails.len(), df.height());
+}
+
 pub async fn get_client() -> Client<Compat<TcpStream>> {
     ssql::utils::get_client("username", "password", "host", "database").await
 }
@@ -35,7 +137,7 @@ pub struct Test {
     pub(crate) stock_in_day: Option<String>,
     pub(crate) total_value: Option<f64>,
     pub(crate) Week: Option<i64>,
-    // pub(crate) Generated_Time: Option<NaiveDateTime>,
+    pub(crate) Generated_Time: Option<NaiveDateTime>,
 }
 
 #[derive(ORM, Debug, Default)]
@@ -45,6 +147,9 @@ pub struct Person {
     pub(crate) Email: String,
 }
 
+#[derive(Debug)]
+pub struct Email(pub String);
+
 #[derive(ORM, Debug, Default)]
 #[ssql(table = FORECAST, schema = UPDATED_DATA)]
 pub struct Fcst {
@@ -4,6 +4,7 @@ use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
 use ssql::prelude::*;
+use ssql::SsqlError;
 
 #[tokio::test]
 async fn query() -> SsqlResult<()> {
@@ -31,6 +32,37 @@ async fn stream() {
     }
 }
 
+#[tokio::test]
+async fn stream_helpers() -> SsqlResult<()> {
+    use futures_lite::stream::StreamExt;
+    let mut client = get_client().await;
+    let query = Person::query();
+    let all = query.stream(&mut client).await?.try_collect().await?;
+
+    let mut count = 0;
+    {
+        let mut chunks = query.stream(&mut client).await?.chunks(2);
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk?;
+            assert!(chunk.len() <= 2);
+            count += chunk.len();
+        }
+    }
+    assert_eq!(count, all.len());
+
+    let emails = std::sync::Mutex::new(vec![]);
+    query
+        .stream(&mut client)
+        .await?
+        .for_each_concurrent(4, |p| {
+            emails.lock().unwrap().push(p.Email);
+            async { Ok(()) }
+        })
+        .await?;
+    assert_eq!(emails.into_inner().unwrap().len(), all.len());
+    Ok(())
+}
+
 #[tokio::test]
 async fn filter() -> SsqlResult<()> {
     let mut client = get_client().await;
@@ -42,6 +74,53 @@ async fn filter() -> SsqlResult<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn filter_over_parameter_limit() -> SsqlResult<()> {
+    let mut client = get_client().await;
+    let ids: Vec<i32> = (0..3000).collect();
+    let r1 = Person::query()
+        .filter(Person::col("id")?.is_in(&ids))?
+        .all(&mut client)
+        .await?;
+    let r2 = Person::query()
+        .in_list_strategy(ssql::InListStrategy::StringSplit)
+        .filter(Person::col("id")?.is_in(&ids))?
+        .all(&mut client)
+        .await?;
+    assert_eq!(r1.len(), r2.len());
+    assert!(Person::query()
+        .in_list_strategy(ssql::InListStrategy::Params)
+        .filter(Person::col("id")?.is_in(&ids))
+        .is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn parameter_limit_boundary() -> SsqlResult<()> {
+    let mut client = get_client().await;
+    // sp_executesql takes 2 of the 2100 parameters of a request
+    let ids: Vec<i32> = (0..2098).collect();
+    let query = Person::query()
+        .in_list_strategy(ssql::InListStrategy::Params)
+        .filter(Person::ID.is_in(&ids))?;
+    query.all(&mut client).await?;
+    assert!(query
+        .update_set(&[(Person::EMAIL.expr(), &"limit")], &mut client)
+        .await
+        .is_err());
+
+    let ids: Vec<i32> = (0..2099).collect();
+    assert!(Person::query()
+        .in_list_strategy(ssql::InListStrategy::Params)
+        .filter(Person::ID.is_in(&ids))
+        .is_err());
+    Person::query()
+        .filter(Person::ID.is_in(&ids))?
+        .all(&mut client)
+        .await?;
+    Ok(())
+}
+
 #[tokio::test]
 async fn insert_many() {
     let mut conn = get_client().await;
@@ -63,6 +142,80 @@ async fn insert_many() {
     assert_eq!(a.unwrap(), 2);
 }
 
+#[tokio::test]
+async fn insert_many_with_options() {
+    let mut conn = get_client().await;
+    let it = (10..20).map(|id| Person {
+        id,
+        Email: "a".to_string(),
+        dt: None,
+    });
+    let options = BulkOptions::new().batch_size(3).tablock();
+    let ret = Person::insert_many_with(it, &options, &mut conn).await;
+    assert_eq!(ret.unwrap(), 10);
+}
+
+#[tokio::test]
+async fn insert_many_with_readonly_column() -> SsqlResult<()> {
+    let mut conn = get_client().await;
+    conn.simple_query(
+        "DROP TABLE IF EXISTS Accounts; \
+        CREATE TABLE Accounts (id INT PRIMARY KEY, created DATETIME2 NOT NULL DEFAULT SYSDATETIME(), name NVARCHAR(50) NOT NULL)",
+    )
+    .await?
+    .into_results()
+    .await?;
+    let accounts = (1..=3).map(|id| Account {
+        id,
+        created: None,
+        name: format!("account {}", id),
+    });
+    assert_eq!(Account::insert_many(accounts, &mut conn).await?, 3);
+    let found = Account::query()
+        .order_by_asc(Account::ID)?
+        .all(&mut conn)
+        .await?;
+    assert_eq!(found.len(), 3);
+    assert_eq!(found[2].name, "account 3");
+    assert!(found.iter().all(|a| a.created.is_some()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn insert_many_column_mismatch() {
+    let mut conn = get_client().await;
+    let item = PersonRenamed {
+        id: 30,
+        mail: "renamed".to_string(),
+        dt: None,
+    };
+    let err = PersonRenamed::insert_many(vec![item], &mut conn)
+        .await
+        .unwrap_err();
+    assert!(matches!(&err, SsqlError::RsRunningError(_)), "{}", err);
+    let message = err.to_string();
+    assert!(message.contains("columns without corresponding field: [Email]"), "{}", message);
+    assert!(message.contains("fields without corresponding column: [mail]"), "{}", message);
+}
+
+#[tokio::test]
+async fn update_and_delete_many() -> SsqlResult<()> {
+    let mut conn = get_client().await;
+    let persons = Person::query().all(&mut conn).await?;
+    let count = persons.len() as u64;
+    let updated = Person::update_many(
+        persons.clone().into_iter().map(|mut p| {
+            p.Email = "many".to_string();
+            p
+        }),
+        &mut conn,
+    )
+    .await?;
+    assert_eq!(updated, count);
+    assert_eq!(Person::delete_many(persons, &mut conn).await?, count);
+    Ok(())
+}
+
 #[tokio::test]
 async fn insert_one() {
     let mut conn = get_client().await;
@@ -97,6 +250,531 @@ async fn update() {
     assert_eq!(p.update(&mut conn).await.is_ok(), true);
 }
 
+#[tokio::test]
+async fn update_and_delete_by_filter() -> SsqlResult<()> {
+    let mut conn = get_client().await;
+    let query = Person::query().filter(Person::col("id")?.gt(&100))?;
+    query
+        .update_set(&[(Person::col("Email")?, &"updated")], &mut conn)
+        .await?;
+    query.delete(&mut conn).await?;
+    assert!(query.all(&mut conn).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn partial_update() -> SsqlResult<()> {
+    let mut conn = get_client().await;
+    let p = Person {
+        id: 99,
+        Email: "".to_string(),
+        dt: None,
+    };
+    p.update_fields(&[Person::col("Email")?], &mut conn).await?;
+
+    let mut p = Tracked::new(p);
+    assert!(!p.is_dirty());
+    p.Email = "tracked".to_string();
+    assert_eq!(p.changed_columns().len(), 1);
+    p.update(&mut conn).await?;
+    assert!(!p.is_dirty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn typed_columns() -> SsqlResult<()> {
+    assert_eq!(Person::EMAIL.name(), "Email");
+    assert_eq!(PersonAlias::EMAIL.name(), "Email");
+    let mut conn = get_client().await;
+    let query = Person::query()
+        .filter(Person::ID.is_in(&[1, 2, 3]))?
+        .filter(Person::EMAIL.eq(&"a@gmail.com").or(Person::DT.is_null()))?
+        .order_by_asc(Person::ID)?;
+    query
+        .update_set(&[(Person::EMAIL.expr(), &"typed")], &mut conn)
+        .await?;
+    let orders = Order::query()
+        .filter(Order::PRIORITY.eq(&Priority::High))?
+        .all(&mut conn)
+        .await?;
+    assert!(orders.iter().all(|o| o.priority == Some(Priority::High)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn optimistic_concurrency() -> SsqlResult<()> {
+    let mut conn = get_client().await;
+    let mut p1 = Versioned::query().one(&mut conn).await?.unwrap();
+    let p2 = Versioned::query().one(&mut conn).await?.unwrap();
+    p1.Email = "first".to_string();
+    p1.update_refresh(&mut conn).await?;
+    p1.update_refresh(&mut conn).await?;
+    assert!(matches!(
+        p2.update(&mut conn).await,
+        Err(SsqlError::ConcurrencyConflict(_))
+    ));
+
+    // a conflict in update_many only rolls back its own changes inside an outer transaction
+    conn.simple_query("BEGIN TRANSACTION").await?.into_results().await?;
+    p1.Email = "outer".to_string();
+    p1.update_refresh(&mut conn).await?;
+    assert!(matches!(
+        Versioned::update_many([p2], &mut conn).await,
+        Err(SsqlError::ConcurrencyConflict(_))
+    ));
+    let open = conn
+        .simple_query("SELECT @@TRANCOUNT")
+        .await?
+        .into_row()
+        .await?
+        .and_then(|r| r.get::<i32, _>(0));
+    assert_eq!(open, Some(1));
+    let current = Versioned::get(&p1.id, &mut conn).await?.unwrap();
+    assert_eq!(current.Email, "outer");
+    conn.simple_query("ROLLBACK").await?.into_results().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn get_by_primary_key() -> SsqlResult<()> {
+    let mut conn = get_client().await;
+    assert!(Person::get(&5, &mut conn).await.is_ok());
+    let ids: Vec<i32> = (0..3000).rev().collect();
+    let keys: Vec<&dyn ToSql> = ids.iter().map(|i| i as &dyn ToSql).collect();
+    let persons = Person::get_many(&keys, &mut conn).await?;
+    assert!(persons.windows(2).all(|w| w[0].id > w[1].id));
+    // i64 keys find i32 rows on server side but can not be matched back to them
+    assert!(Person::get_many(&[&5_i64], &mut conn).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn composite_primary_key() -> SsqlResult<()> {
+    let mut conn = get_client().await;
+    let link = PersonLogs { person_id: 1, log_id: 2 };
+    link.insert(&mut conn).await?;
+    let found = PersonLogs::find_by_pk(&[&1, &2], &mut conn).await?;
+    assert!(found.is_some());
+    found.unwrap().delete(&mut conn).await?;
+    assert!(PersonLogs::find_by_pk(&[&1, &2], &mut conn).await?.is_none());
+    assert!(PersonLogs::find_by_pk(&[&1], &mut conn).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn field_attributes() -> SsqlResult<()> {
+    let mut conn = get_client().await;
+    assert_eq!(PersonAlias::fields(), vec!["id", "Email", "dt"]);
+    assert_eq!(PersonAlias::readonly_fields(), vec!["dt"]);
+    let alias = PersonAlias::query()
+        .filter(PersonAlias::col("Email")?.eq(&"a@gmail.com"))?
+        .one(&mut conn)
+        .await?;
+    assert!(alias.is_some_and(|p| p.notes.is_empty()));
+    assert!(PersonAlias::default()
+        .update_fields(&[PersonAlias::col("dt")?], &mut conn)
+        .await
+        .is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn quoted_identifiers() -> SsqlResult<()> {
+    assert_eq!(ssql::utils::quote_name("dbo.Order]s"), "[dbo].[Order]]s]");
+    let mut conn = get_client().await;
+    let detail = OrderDetail {
+        id: 1,
+        order_date: None,
+    };
+    detail.insert(&mut conn).await?;
+    let found = OrderDetail::query()
+        .filter(OrderDetail::col("Order Date")?.is_null())?
+        .all(&mut conn)
+        .await?;
+    assert!(!found.is_empty());
+    OrderDetail::get(&1, &mut conn).await?.unwrap().delete(&mut conn).await
+}
+
+#[test]
+fn foreign_keys_to_same_column() {
+    // joins go through the first relationship declared
+    assert_eq!(
+        Post::relationship("Person"),
+        Some("[Posts].[created_by] = [Person].[id]")
+    );
+}
+
+#[tokio::test]
+async fn tenant_schema() -> SsqlResult<()> {
+    let mut conn = get_client().await;
+    let scope = TableScope::schema("tenant42");
+    assert_eq!(scope.resolve("Person"), "[tenant42].[Person]");
+    let person = Person {
+        id: 99,
+        Email: "tenant@gmail.com".to_string(),
+        dt: None,
+    };
+    person.insert_in(&scope, &mut conn).await?;
+    let mut persons = Person::query()
+        .in_scope(scope.clone())
+        .filter(Person::col("id")?.eq(&99))?
+        .all(&mut conn)
+        .await?;
+    assert_eq!(persons.len(), 1);
+    persons.pop().unwrap().delete_in(&scope, &mut conn).await
+}
+
+#[tokio::test]
+async fn tenant_schema_update_and_delete() -> SsqlResult<()> {
+    let mut conn = get_client().await;
+    let scope = TableScope::schema("tenant42");
+    let persons: Vec<Person> = (100..110)
+        .map(|id| Person {
+            id,
+            Email: format!("{id}@gmail.com"),
+            dt: None,
+        })
+        .collect();
+    let options = BulkOptions::new().scope(scope.clone());
+    Person::insert_many_with(persons.clone(), &options, &mut conn).await?;
+
+    let mut person = Person::get_in(&scope, &100, &mut conn).await?.unwrap();
+    person.Email = "single@gmail.com".to_string();
+    person.update_fields_in(&scope, &[Person::col("Email")?], &mut conn).await?;
+    let mut person = Tracked::new(Person::find_by_pk_in(&scope, &[&100], &mut conn).await?.unwrap());
+    assert_eq!(person.Email, "single@gmail.com");
+    person.Email = "tracked@gmail.com".to_string();
+    person.update_in(&scope, &mut conn).await?;
+
+    let updated = persons.into_iter().skip(1).map(|mut p| {
+        p.Email = "many@gmail.com".to_string();
+        p
+    });
+    assert_eq!(Person::update_many_in(&scope, updated, &mut conn).await?, 9);
+    let ids: Vec<i32> = (100..110).collect();
+    let keys: Vec<&dyn ToSql> = ids.iter().map(|id| id as &dyn ToSql).collect();
+    let persons = Person::get_many_in(&scope, &keys, &mut conn).await?;
+    assert_eq!(persons[0].Email, "tracked@gmail.com");
+    assert!(persons[1..].iter().all(|p| p.Email == "many@gmail.com"));
+    assert_eq!(Person::delete_many_in(&scope, persons, &mut conn).await?, 10);
+    assert!(Person::get_in(&scope, &100, &mut conn).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn custom_conversion() -> SsqlResult<()> {
+    let mut conn = get_client().await;
+    let item = Tagged {
+        id: 7,
+        Email: Email("tagged@gmail.com".to_string()),
+        tags: vec!["a".to_string(), "b".to_string()],
+    };
+    assert_eq!(
+        item.field_values()?[2],
+        ColumnData::String(Some("a,b".into()))
+    );
+    item.insert(&mut conn).await?;
+    let found = Tagged::get(&7, &mut conn).await?.unwrap();
+    assert_eq!(found.tags, vec!["a", "b"]);
+    assert_eq!(found.Email.0, "tagged@gmail.com");
+    Tagged::insert_many(vec![found], &mut conn).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn enum_fields() -> SsqlResult<()> {
+    assert_eq!(
+        OrderStatus::Shipped.to_sql(),
+        ColumnData::String(Some("SHIPPED".into()))
+    );
+    assert_eq!(Priority::High.to_sql(), ColumnData::U8(Some(10)));
+    let mut conn = get_client().await;
+    let order = Order {
+        id: 1,
+        status: OrderStatus::Open,
+        priority: None,
+    };
+    order.insert(&mut conn).await?;
+    let orders = (2..4).map(|id| Order {
+        id,
+        status: OrderStatus::Open,
+        priority: Some(Priority::High),
+    });
+    Order::insert_many(orders, &mut conn).await?;
+    let mut order = Order::get(&1, &mut conn).await?.unwrap();
+    assert_eq!(order.priority, None);
+    order.priority = Some(Priority::High);
+    order.update(&mut conn).await?;
+    let orders = Order::query()
+        .filter(Order::col("status")?.eq(&OrderStatus::Open))?
+        .all(&mut conn)
+        .await?;
+    assert!(orders
+        .iter()
+        .all(|o| o.status == OrderStatus::Open && o.priority == Some(Priority::High)));
+    Order::delete_many(orders, &mut conn).await?;
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[tokio::test]
+async fn json_fields() -> SsqlResult<()> {
+    let mut conn = get_client().await;
+    let event = Event {
+        id: 1,
+        payload: Payload {
+            customer: Customer { id: 42 },
+            tags: vec!["vip".to_string()],
+        },
+        extra: None,
+    };
+    assert_eq!(
+        event.field_values()?[1],
+        ColumnData::String(Some(r#"{"customer":{"id":42},"tags":["vip"]}"#.into()))
+    );
+    assert_eq!(event.field_values()?[2], ColumnData::String(None));
+    event.insert(&mut conn).await?;
+    let events = Event::query()
+        .filter(Event::col("payload.$.customer.id")?.eq(&42))?
+        .filter(Event::col("payload.$.tags")?.json_contains(&"vip"))?
+        .all(&mut conn)
+        .await?;
+    assert_eq!(events[0].payload.customer, Customer { id: 42 });
+    assert!(events[0].extra.is_none());
+    assert!(Event::query()
+        .order_by_desc(Event::col("payload")?.json("$.customer.id"))
+        .is_ok());
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[tokio::test]
+async fn json_serialization_error() {
+    // maps with non-string keys can not be serialized to JSON
+    let grid = Grid {
+        id: 1,
+        cells: std::collections::HashMap::from([((0, 0), 1)]),
+    };
+    assert!(grid.field_values().is_err());
+    let mut conn = get_client().await;
+    assert!(grid.insert(&mut conn).await.is_err());
+}
+
+#[cfg(feature = "serde")]
+#[tokio::test]
+async fn flattened_fields() -> SsqlResult<()> {
+    assert_eq!(
+        Supplier::fields(),
+        vec!["id", "address_street", "address_city", "billing_street", "billing_city"]
+    );
+    let mut conn = get_client().await;
+    let supplier = Supplier {
+        id: 1,
+        address: Address {
+            street: "Main Street 1".to_string(),
+            city: Some("Berlin".to_string()),
+        },
+        billing: Address::default(),
+    };
+    assert_eq!(supplier.field_values()?[2], ColumnData::String(Some("Berlin".into())));
+    supplier.insert(&mut conn).await?;
+    let mut found = Supplier::query()
+        .filter(Supplier::col("address_city")?.eq(&"Berlin"))?
+        .one(&mut conn)
+        .await?
+        .unwrap();
+    assert_eq!(found.address.street, "Main Street 1");
+    found.billing.city = Some("Hamburg".to_string());
+    found.update(&mut conn).await?;
+    let json = Supplier::query().json(&mut conn).await?;
+    assert!(json[0]["address"]["street"].is_string());
+    Ok(())
+}
+
+#[tokio::test]
+async fn missing_relationship() {
+    let mut conn = get_client().await;
+    let query = Order::query().left_join::<Supplier>();
+    assert!(matches!(
+        query.all(&mut conn).await,
+        Err(SsqlError::RelationshipNotFound { .. })
+    ));
+}
+
+#[tokio::test]
+async fn classified_errors() -> SsqlResult<()> {
+    let mut conn = get_client().await;
+    let p = Person {
+        id: 1000,
+        Email: "dup".to_string(),
+        dt: None,
+    };
+    p.clone().insert(&mut conn).await?;
+    let err = p.clone().insert(&mut conn).await.unwrap_err();
+    assert!(matches!(err, SsqlError::UniqueViolation(_)));
+    assert!(!err.is_retryable());
+    assert!(err.sql().unwrap().starts_with("INSERT INTO"));
+    p.delete(&mut conn).await?;
+
+    assert!(matches!(
+        Person::col("missing"),
+        Err(SsqlError::ColumnNotFound { .. })
+    ));
+    assert!(matches!(
+        Person::query().filter(Order::ID.eq(&1)),
+        Err(SsqlError::TableNotInBuilder(_))
+    ));
+    assert!(matches!(
+        Person::query()
+            .filter(Person::ID.eq(&-1))?
+            .first(&mut conn)
+            .await,
+        Err(SsqlError::NoRows(_))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn retry_policy() -> SsqlResult<()> {
+    let mut conn = get_client().await;
+    let policy = RetryPolicy::new()
+        .backoff(
+            std::time::Duration::from_millis(1),
+            std::time::Duration::from_millis(10),
+        )
+        .retry_on(|e| matches!(e, SsqlError::NoRows(_)));
+    let attempts = policy
+        .run(&mut conn, |_, attempt| {
+            Box::pin(async move {
+                match attempt.number() {
+                    1 => Err(SsqlError::NoRows("Person".to_string())),
+                    n => Ok(n),
+                }
+            })
+        })
+        .await?;
+    assert_eq!(attempts, 2);
+    assert!(Person::query().retry(&policy).all(&mut conn).await.is_ok());
+
+    // not retried inside a transaction opened outside of the unit
+    conn.simple_query("BEGIN TRANSACTION").await?.into_results().await?;
+    let ret = policy
+        .run(&mut conn, |_, attempt| {
+            Box::pin(async move {
+                match attempt.number() {
+                    1 => Err(SsqlError::NoRows("Person".to_string())),
+                    n => Ok(n),
+                }
+            })
+        })
+        .await;
+    assert!(matches!(ret, Err(SsqlError::NoRows(_))));
+    conn.simple_query("ROLLBACK").await?.into_results().await?;
+
+    // a transaction left open by a later attempt is rolled back before the next one
+    let attempts = policy
+        .run(&mut conn, |conn, attempt| {
+            Box::pin(async move {
+                let open = conn
+                    .simple_query("SELECT @@TRANCOUNT")
+                    .await?
+                    .into_row()
+                    .await?
+                    .and_then(|r| r.get::<i32, _>(0));
+                assert_eq!(open, Some(0));
+                match attempt.number() {
+                    1 => Err(SsqlError::NoRows("Person".to_string())),
+                    2 => {
+                        conn.simple_query("BEGIN TRANSACTION").await?.into_results().await?;
+                        Err(SsqlError::NoRows("Person".to_string()))
+                    }
+                    n => Ok(n),
+                }
+            })
+        })
+        .await?;
+    assert_eq!(attempts, 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn retry_deadlock_in_transaction() -> SsqlResult<()> {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    let mut conn = get_client().await;
+    let mut other = get_client().await;
+    conn.simple_query(
+        "DROP TABLE IF EXISTS ##ssql_deadlock; \
+        CREATE TABLE ##ssql_deadlock (id INT PRIMARY KEY, val INT); \
+        INSERT INTO ##ssql_deadlock VALUES (1, 0), (2, 0); \
+        SET DEADLOCK_PRIORITY LOW",
+    )
+    .await?
+    .into_results()
+    .await?;
+
+    // the outer transaction locks row 1, the other connection row 2 and then waits for row 1
+    conn.simple_query("BEGIN TRANSACTION; UPDATE ##ssql_deadlock SET val = 1 WHERE id = 1")
+        .await?
+        .into_results()
+        .await?;
+    other
+        .simple_query("BEGIN TRANSACTION; UPDATE ##ssql_deadlock SET val = 2 WHERE id = 2")
+        .await?
+        .into_results()
+        .await?;
+    let waiting = tokio::spawn(async move {
+        other
+            .simple_query("UPDATE ##ssql_deadlock SET val = 2 WHERE id = 1")
+            .await?
+            .into_results()
+            .await?;
+        other.simple_query("ROLLBACK").await?.into_results().await?;
+        SsqlResult::Ok(())
+    });
+
+    // the deadlock rolls back the outer transaction, which must not be retried in pieces
+    let runs = AtomicU32::new(0);
+    let ret = RetryPolicy::new()
+        .backoff(
+            std::time::Duration::from_millis(1),
+            std::time::Duration::from_millis(10),
+        )
+        .run(&mut conn, |conn, _| {
+            runs.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                conn.simple_query("UPDATE ##ssql_deadlock SET val = 1 WHERE id = 2")
+                    .await?
+                    .into_results()
+                    .await?;
+                Ok(())
+            })
+        })
+        .await;
+    assert!(matches!(ret, Err(SsqlError::Deadlock(_))));
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+    waiting.await.unwrap()?;
+    conn.simple_query("DROP TABLE ##ssql_deadlock")
+        .await?
+        .into_results()
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn decode_errors() -> SsqlResult<()> {
+    use futures_lite::stream::StreamExt;
+    let mut conn = get_client().await;
+    let query = PersonMistyped::query().filter(PersonMistyped::ID.eq(&"99"))?;
+    let err = query.all(&mut conn).await.unwrap_err();
+    assert!(matches!(&err, SsqlError::Decode { column, .. } if column == "id"), "{}", err);
+    assert!(err.to_string().contains("String"), "{}", err);
+    let mut stream = query.stream(&mut conn).await?;
+    while let Some(row) = stream.next().await {
+        assert!(row.is_err());
+    }
+    Ok(())
+}
+
 #[tokio::test]
 async fn raw_query_and_chrono() {
     use futures_lite::stream::StreamExt;
@@ -151,6 +829,167 @@ pub struct Person {
     dt: Option<NaiveDateTime>,
 }
 
+#[derive(ORM, Debug, Clone, Default)]
+#[ssql(table = VersionedPerson)]
+pub struct Versioned {
+    #[ssql(primary_key)]
+    pub(crate) id: i32,
+    pub(crate) Email: String,
+    #[ssql(version)]
+    pub(crate) row_ver: Vec<u8>,
+}
+
+#[derive(ORM, Debug, Default)]
+#[ssql(table = Person)]
+pub struct PersonAlias {
+    #[ssql(primary_key)]
+    pub(crate) id: i32,
+    #[ssql(rename = "Email")]
+    pub(crate) email: String,
+    #[ssql(readonly)]
+    dt: Option<NaiveDateTime>,
+    #[ssql(skip)]
+    notes: Vec<String>,
+}
+
+#[derive(ORM, Debug)]
+#[ssql(table = "Order Details")]
+pub struct OrderDetail {
+    #[ssql(primary_key)]
+    id: i32,
+    #[ssql(rename = "Order Date")]
+    order_date: Option<NaiveDateTime>,
+}
+
+#[derive(Debug)]
+pub struct Email(String);
+
+mod email {
+    use ssql::prelude::*;
+
+    pub fn to_sql(value: &super::Email) -> ColumnData<'static> {
+        ColumnData::String(Some(value.0.clone().into()))
+    }
+
+    pub fn from_sql(data: &ColumnData<'static>) -> SsqlResult<super::Email> {
+        let text: Option<&str> = tiberius::FromSql::from_sql(data)?;
+        Ok(super::Email(text.unwrap_or_default().to_string()))
+    }
+}
+
+mod comma_list {
+    use ssql::prelude::*;
+
+    pub fn to_sql(value: &[String]) -> ColumnData<'static> {
+        ColumnData::String(Some(value.join(",").into()))
+    }
+
+    pub fn from_sql(data: &ColumnData<'static>) -> SsqlResult<Vec<String>> {
+        let text: Option<&str> = tiberius::FromSql::from_sql(data)?;
+        Ok(text
+            .map(|t| t.split(',').map(String::from).collect())
+            .unwrap_or_default())
+    }
+}
+
+#[derive(ORM, Debug)]
+#[ssql(table = TaggedPerson)]
+pub struct Tagged {
+    #[ssql(primary_key)]
+    pub(crate) id: i32,
+    #[ssql(with = "email")]
+    pub(crate) Email: Email,
+    #[ssql(with = "comma_list")]
+    pub(crate) tags: Vec<String>,
+}
+
+#[derive(SsqlEnum, Serialize, Debug, PartialEq)]
+pub enum OrderStatus {
+    #[ssql(rename = "OPEN")]
+    Open,
+    #[ssql(rename = "SHIPPED")]
+    Shipped,
+}
+
+#[derive(SsqlEnum, Serialize, Debug, PartialEq)]
+#[ssql(repr = "u8")]
+pub enum Priority {
+    Low = 1,
+    High = 10,
+}
+
+#[derive(ColumnGroup, Debug, Default, Serialize)]
+pub struct Address {
+    pub(crate) street: String,
+    pub(crate) city: Option<String>,
+}
+
+#[derive(ORM, Debug)]
+#[ssql(table = Suppliers)]
+pub struct Supplier {
+    #[ssql(primary_key)]
+    pub(crate) id: i32,
+    #[ssql(flatten)]
+    pub(crate) address: Address,
+    #[ssql(flatten, prefix = "billing_")]
+    pub(crate) billing: Address,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Customer {
+    pub(crate) id: i32,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Payload {
+    pub(crate) customer: Customer,
+    pub(crate) tags: Vec<String>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(ORM, Debug)]
+#[ssql(table = Grids)]
+pub struct Grid {
+    #[ssql(primary_key)]
+    pub(crate) id: i32,
+    #[ssql(json)]
+    pub(crate) cells: std::collections::HashMap<(i32, i32), i32>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(ORM, Debug)]
+#[ssql(table = Events)]
+pub struct Event {
+    #[ssql(primary_key)]
+    pub(crate) id: i32,
+    #[ssql(json)]
+    pub(crate) payload: Payload,
+    #[ssql(json)]
+    pub(crate) extra: Option<Vec<i32>>,
+}
+
+#[derive(ORM, Debug)]
+#[ssql(table = Orders)]
+pub struct Order {
+    #[ssql(primary_key)]
+    pub(crate) id: i32,
+    pub(crate) status: OrderStatus,
+    pub(crate) priority: Option<Priority>,
+}
+
+#[derive(ORM, Debug)]
+#[ssql(table = Posts)]
+pub struct Post {
+    #[ssql(primary_key)]
+    pub(crate) id: i32,
+    #[ssql(foreign_key = "Person.id")]
+    pub(crate) created_by: i32,
+    #[ssql(foreign_key = "Person.id")]
+    pub(crate) updated_by: i32,
+}
+
 #[derive(ORM, Debug, Default, Serialize, Deserialize)]
 #[ssql(table)]
 pub struct PersonRaw {
@@ -177,3 +1016,37 @@ struct Logs {
     id: i32,
     num: String,
 }
+
+#[derive(ORM, Debug)]
+#[ssql(table = PersonLogs)]
+struct PersonLogs {
+    #[ssql(primary_key)]
+    person_id: i32,
+    #[ssql(primary_key)]
+    log_id: i32,
+}
+
+#[derive(ORM, Debug)]
+#[ssql(table = Accounts)]
+struct Account {
+    #[ssql(primary_key)]
+    id: i32,
+    #[ssql(readonly)]
+    created: Option<NaiveDateTime>,
+    name: String,
+}
+
+#[derive(ORM, Debug)]
+#[ssql(table = Person)]
+struct PersonRenamed {
+    #[ssql(primary_key)]
+    id: i32,
+    mail: String,
+    dt: Option<NaiveDateTime>,
+}
+
+#[derive(ORM, Debug)]
+#[ssql(table = Person)]
+struct PersonMistyped {
+    id: String,
+}